@@ -1,8 +1,10 @@
 use aws_sdk_s3::{
     operation::{
-        delete_object::DeleteObjectOutput, get_object::GetObjectOutput, put_object::PutObjectOutput,
+        delete_object::DeleteObjectOutput, get_object::GetObjectOutput,
+        head_object::HeadObjectOutput, put_object::PutObjectOutput,
     },
     presigning::PresigningConfig,
+    types::{Delete, ObjectIdentifier},
     Client,
 };
 use std::time::Duration;
@@ -11,6 +13,10 @@ use crate::errors::AppError;
 
 use super::config;
 
+// most keys `DeleteObjects` accepts in one request
+const DELETE_BATCH_SIZE: usize = 1_000;
+
+#[derive(Debug, Clone)]
 pub struct Bucket {
     pub name: String,
     pub client: Client,
@@ -68,6 +74,87 @@ impl Bucket {
             .map_err(AppError::internal_server_error)
     }
 
+    /// the object's metadata, or `None` if there is no object at `key`
+    pub async fn head_object(
+        &self,
+        key: impl ToString,
+    ) -> Result<Option<HeadObjectOutput>, AppError> {
+        let head = self
+            .client
+            .head_object()
+            .bucket(&self.name)
+            .key(key.to_string())
+            .send()
+            .await;
+        match head {
+            Ok(head) => Ok(Some(head)),
+            Err(err) => match err.into_service_error() {
+                err if err.is_not_found() => Ok(None),
+                err => Err(AppError::internal_server_error(err)),
+            },
+        }
+    }
+
+    /// every key under `prefix`, following continuation tokens
+    pub async fn list_objects(&self, prefix: impl ToString) -> Result<Vec<String>, AppError> {
+        let prefix = prefix.to_string();
+        let mut keys = vec![];
+        let mut continuation_token = None;
+        loop {
+            let page = self
+                .client
+                .list_objects_v2()
+                .bucket(&self.name)
+                .prefix(&prefix)
+                .set_continuation_token(continuation_token)
+                .send()
+                .await
+                .map_err(AppError::internal_server_error)?;
+            keys.extend(
+                page.contents()
+                    .iter()
+                    .filter_map(|object| object.key().map(ToString::to_string)),
+            );
+            match page.next_continuation_token() {
+                Some(token) => continuation_token = Some(token.to_string()),
+                None => return Ok(keys),
+            }
+        }
+    }
+
+    /// deletes every object under `prefix`, returning how many were removed
+    pub async fn delete_prefix(&self, prefix: impl ToString) -> Result<usize, AppError> {
+        let keys = self.list_objects(prefix).await?;
+        for batch in keys.chunks(DELETE_BATCH_SIZE) {
+            let objects = batch
+                .iter()
+                .map(|key| ObjectIdentifier::builder().key(key).build())
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(AppError::internal_server_error)?;
+            let delete = Delete::builder()
+                .set_objects(Some(objects))
+                .quiet(true)
+                .build()
+                .map_err(AppError::internal_server_error)?;
+            let output = self
+                .client
+                .delete_objects()
+                .bucket(&self.name)
+                .delete(delete)
+                .send()
+                .await
+                .map_err(AppError::internal_server_error)?;
+            if let Some(error) = output.errors().first() {
+                return Err(AppError::internal_server_error(format!(
+                    "failed to delete {}: {}",
+                    error.key().unwrap_or_default(),
+                    error.message().unwrap_or_default()
+                )));
+            }
+        }
+        Ok(keys.len())
+    }
+
     pub async fn get_presigned_url(
         &self,
         key: impl ToString,
@@ -99,4 +186,23 @@ impl Bucket {
             .map_err(AppError::internal_server_error)?;
         Ok(request.uri().to_string())
     }
+
+    /// a presigned `PUT` that only accepts uploads sent with `Content-Type: {content_type}`
+    pub async fn put_presigned_url_with_content_type(
+        &self,
+        key: impl ToString,
+        content_type: &str,
+        expires_in: Duration,
+    ) -> Result<String, AppError> {
+        let request = self
+            .client
+            .put_object()
+            .bucket(&self.name)
+            .key(key.to_string())
+            .content_type(content_type)
+            .presigned(Self::_build_presigned_config(expires_in)?)
+            .await
+            .map_err(AppError::internal_server_error)?;
+        Ok(request.uri().to_string())
+    }
 }
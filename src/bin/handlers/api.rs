@@ -1,10 +1,10 @@
 use lambda_http::Error;
 use pixel_collector_api::{
-    aws::dynamo::{self},
+    aws::{dynamo, s3::Bucket},
     cache,
     controllers::routes,
     env::Env,
-    logger,
+    logger, oauth,
     types::{AppState, ONE_MINUTE_IN_MS},
 };
 
@@ -14,10 +14,14 @@ pub async fn main() -> Result<(), Error> {
     let env = Env::load()?;
     let state = AppState {
         dynamo: dynamo::connect().await,
+        bucket: Bucket::new(&env.bucket_name).await,
+        oauth: oauth::Registry::from_env(&env),
         env,
         stage_cache: cache::prepare(10_000, ONE_MINUTE_IN_MS),
     };
-    let app = axum::Router::new().nest("/", routes()).with_state(state);
+    let app = axum::Router::new()
+        .nest("/", routes(&state))
+        .with_state(state);
     if cfg!(debug_assertions) {
         let listener = tokio::net::TcpListener::bind("localhost:3000").await?;
         tracing::info!("listening on {:?}", listener.local_addr()?);
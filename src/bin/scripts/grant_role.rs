@@ -0,0 +1,22 @@
+use mongoose::Model;
+use pixel_collector_api::{errors::AppError, logger, models::user::User, rbac::Role};
+
+/// bootstraps admins without an existing admin token:
+/// `cargo run --bin grant_role -- <user_id> <role>`
+#[tokio::main]
+async fn main() -> Result<(), AppError> {
+    logger::init()?;
+    let mut args = std::env::args().skip(1);
+    let (Some(user_id), Some(role)) = (args.next(), args.next()) else {
+        return Err(AppError::bad_request("usage: grant_role <user_id> <role>"));
+    };
+    let role = role.parse::<Role>()?;
+    let user = User::read_by_id(&user_id)
+        .await
+        .map_err(AppError::not_found)?;
+    let mut roles = user.roles;
+    roles.push(role);
+    let user = User::set_roles(&user_id, &roles).await?;
+    tracing::info!("[{}] roles: {:?}", user.id, user.roles);
+    Ok(())
+}
@@ -1,15 +1,40 @@
 use pixel_collector_api::{
+    env::Env,
     errors::AppError,
     logger,
-    models::{oauth_link_state::LinkState, user::User},
+    models::{
+        authorization_code::AuthorizationCode, deleted_user::DeletedUser,
+        oauth_link_state::LinkState, refresh_token::RefreshToken, user::User,
+        user_status_event::UserStatusEvent,
+    },
 };
 use tokio::try_join;
 
 #[tokio::main]
 async fn main() -> Result<(), AppError> {
     logger::init()?;
-    let indexes = try_join!(LinkState::migrate(), User::migrate())
+    let env = Env::load()?;
+    let oidc_providers = env
+        .oidc_providers
+        .iter()
+        .map(|config| config.name.to_string())
+        .collect::<Vec<_>>();
+    // users first: the oidc indexes are per service, so they wait for its backfill
+    let users = User::migrate()
+        .await
         .map_err(AppError::internal_server_error)?;
+    let oidc = User::migrate_oidc(&oidc_providers)
+        .await
+        .map_err(AppError::internal_server_error)?;
+    tracing::info!("{:#?}", (users, oidc));
+    let indexes = try_join!(
+        LinkState::migrate(),
+        RefreshToken::migrate(),
+        AuthorizationCode::migrate(),
+        UserStatusEvent::migrate(),
+        DeletedUser::migrate()
+    )
+    .map_err(AppError::internal_server_error)?;
     tracing::info!("{:#?}", indexes);
     Ok(())
 }
@@ -0,0 +1,30 @@
+use bson::doc;
+use mongoose::{types::ListOptions, Model};
+use pixel_collector_api::{env::Env, errors::AppError, logger, models::user::User, oauth};
+
+#[tokio::main]
+async fn main() -> Result<(), AppError> {
+    logger::init()?;
+    let env = Env::load()?;
+    let registry = oauth::Registry::from_env(&env);
+    for provider in registry.providers() {
+        let key = provider.provider().auth_key();
+        let users = User::list(
+            doc! { (format!("auth.{key}.tokens.refresh_token")): { "$exists": true, "$ne": "" } },
+            ListOptions {
+                limit: 0,
+                ..Default::default()
+            },
+        )
+        .await
+        .map_err(AppError::not_found)?;
+        for user in users {
+            // one revoked grant shouldn't stop the rest from refreshing
+            match user.refresh_provider_tokens(provider.as_ref()).await {
+                Ok(updated) => tracing::info!("[{key}] user updated: {:?}", updated.id),
+                Err(err) => tracing::error!("[{key}] error refreshing {:?}: {err:?}", user.id),
+            }
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,129 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Json,
+};
+use mongoose::DateTime;
+
+use crate::{
+    errors::AppError,
+    middleware::AuthUser,
+    models::{
+        user::{AccountStatus, AdminAccount, User},
+        user_status_event::UserStatusEvent,
+    },
+    types::{ApiResponse, AppState, BanUser, SetRoles, SuspendUser, UserListQuery},
+};
+
+pub async fn list_users(
+    AuthUser(admin): AuthUser,
+    Query(query): Query<UserListQuery>,
+) -> ApiResponse {
+    let page = User::list_page(&admin.service, &query).await?;
+    Ok(Json(page).into_response())
+}
+
+pub async fn read_user(AuthUser(admin): AuthUser, Path(id): Path<String>) -> ApiResponse {
+    let user = User::read_in_service(&id, &admin.service).await?;
+    Ok(Json(AdminAccount::from(user)).into_response())
+}
+
+pub async fn status_history(AuthUser(admin): AuthUser, Path(id): Path<String>) -> ApiResponse {
+    User::read_in_service(&id, &admin.service).await?;
+    let events = UserStatusEvent::history(&id).await?;
+    Ok(Json(events).into_response())
+}
+
+pub async fn suspend_user(
+    AuthUser(admin): AuthUser,
+    Path(id): Path<String>,
+    Json(body): Json<SuspendUser>,
+) -> ApiResponse {
+    if admin.id == id {
+        return Err(AppError::bad_request("you cannot suspend yourself"));
+    }
+    let status = AccountStatus::Suspended {
+        until: body
+            .until
+            .map(|until| DateTime::from_millis(until.timestamp_millis())),
+        reason: body.reason,
+    };
+    User::read_in_service(&id, &admin.service).await?;
+    let user = User::set_status(&id, status, Some(&admin.id)).await?;
+    tracing::info!("[ADMIN {}]: suspended user {}", admin.id, user.id);
+    Ok(Json(AdminAccount::from(user)).into_response())
+}
+
+pub async fn ban_user(
+    AuthUser(admin): AuthUser,
+    Path(id): Path<String>,
+    Json(body): Json<BanUser>,
+) -> ApiResponse {
+    if admin.id == id {
+        return Err(AppError::bad_request("you cannot ban yourself"));
+    }
+    let status = AccountStatus::Banned {
+        reason: body.reason,
+    };
+    User::read_in_service(&id, &admin.service).await?;
+    let user = User::set_status(&id, status, Some(&admin.id)).await?;
+    tracing::info!("[ADMIN {}]: banned user {}", admin.id, user.id);
+    Ok(Json(AdminAccount::from(user)).into_response())
+}
+
+/// lifts a suspension or ban
+pub async fn reinstate_user(AuthUser(admin): AuthUser, Path(id): Path<String>) -> ApiResponse {
+    User::read_in_service(&id, &admin.service).await?;
+    let user = User::set_status(&id, AccountStatus::Active, Some(&admin.id)).await?;
+    tracing::info!("[ADMIN {}]: reinstated user {}", admin.id, user.id);
+    Ok(Json(AdminAccount::from(user)).into_response())
+}
+
+pub async fn delete_user(
+    State(state): State<AppState>,
+    AuthUser(admin): AuthUser,
+    Path(id): Path<String>,
+) -> ApiResponse {
+    if admin.id == id {
+        return Err(AppError::bad_request("you cannot delete yourself"));
+    }
+    User::read_in_service(&id, &admin.service).await?;
+    let user = User::delete_account(
+        &state.dynamo,
+        &state.bucket,
+        &state.oauth,
+        &id,
+        Some(&admin.id),
+    )
+    .await?;
+    tracing::info!("[ADMIN {}]: deleted user {}", admin.id, user.id);
+    Ok(StatusCode::NO_CONTENT.into_response())
+}
+
+pub async fn revoke_sessions(AuthUser(admin): AuthUser, Path(id): Path<String>) -> ApiResponse {
+    User::read_in_service(&id, &admin.service).await?;
+    let user = User::revoke_sessions(&id).await?;
+    tracing::info!(
+        "[ADMIN {}]: revoked sessions for user {}",
+        admin.id,
+        user.id
+    );
+    Ok(StatusCode::NO_CONTENT.into_response())
+}
+
+pub async fn set_roles(
+    AuthUser(admin): AuthUser,
+    Path(id): Path<String>,
+    Json(body): Json<SetRoles>,
+) -> ApiResponse {
+    User::read_in_service(&id, &admin.service).await?;
+    let user = User::set_roles(&id, &body.roles).await?;
+    tracing::info!(
+        "[ADMIN {}]: set roles for user {} to {:?}",
+        admin.id,
+        user.id,
+        user.roles
+    );
+    Ok(Json(AdminAccount::from(user)).into_response())
+}
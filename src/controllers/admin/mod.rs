@@ -0,0 +1,53 @@
+use axum::{
+    middleware::from_fn_with_state,
+    routing::{delete, get, post, put},
+};
+
+use crate::{
+    middleware::{require_auth, require_permissions, RequirePermissions},
+    rbac::Permission,
+    types::AppState,
+};
+
+mod controller;
+
+pub fn router(state: &AppState) -> axum::Router<AppState> {
+    let read_users = axum::Router::new()
+        .route("/users", get(controller::list_users))
+        .route("/users/:id", get(controller::read_user))
+        .route("/users/:id/status-history", get(controller::status_history))
+        .route_layer(from_fn_with_state(
+            RequirePermissions(&[Permission::ReadUsers]),
+            require_permissions,
+        ));
+    let write_users = axum::Router::new()
+        .route("/users/:id", delete(controller::delete_user))
+        .route("/users/:id/suspend", post(controller::suspend_user))
+        .route("/users/:id/ban", post(controller::ban_user))
+        .route("/users/:id/reinstate", post(controller::reinstate_user))
+        .route_layer(from_fn_with_state(
+            RequirePermissions(&[Permission::WriteUsers]),
+            require_permissions,
+        ));
+    let sessions = axum::Router::new()
+        .route(
+            "/users/:id/revoke-sessions",
+            post(controller::revoke_sessions),
+        )
+        .route_layer(from_fn_with_state(
+            RequirePermissions(&[Permission::RevokeSessions]),
+            require_permissions,
+        ));
+    let roles = axum::Router::new()
+        .route("/users/:id/roles", put(controller::set_roles))
+        .route_layer(from_fn_with_state(
+            RequirePermissions(&[Permission::WriteRoles]),
+            require_permissions,
+        ));
+    axum::Router::new()
+        .merge(read_users)
+        .merge(write_users)
+        .merge(sessions)
+        .merge(roles)
+        .route_layer(from_fn_with_state(state.clone(), require_auth))
+}
@@ -1,30 +1,97 @@
 use axum::{
     extract::{Json, Path, State},
+    http::StatusCode,
     response::IntoResponse,
 };
+use mongoose::Model;
+use serde_json::json;
 
 use crate::{
-    models::auth::Auth,
-    types::{ApiResponse, AppState, Login},
+    errors::AppError,
+    middleware::RequireService,
+    models::{
+        auth::{Auth, PublicCredentials},
+        refresh_token::RefreshToken,
+        user::User,
+    },
+    types::{ApiResponse, AppState, Login, RefreshTokenBody},
 };
 
 pub async fn read_by_id(State(state): State<AppState>, Path(id): Path<String>) -> ApiResponse {
     let item = Auth::get_by_id(&state.dynamo, &id).await?;
-    Ok(Json(item).into_response())
+    Ok(Json(PublicCredentials::from(item)).into_response())
 }
 
-pub async fn login(State(state): State<AppState>, Json(body): Json<Login>) -> ApiResponse {
-    let item = Auth::login(&state.dynamo, &body.username).await?;
-    Ok(Json(item).into_response())
+pub async fn login(
+    State(state): State<AppState>,
+    RequireService(service): RequireService,
+    Json(body): Json<Login>,
+) -> ApiResponse {
+    let mut auth = Auth::login(&state.dynamo, &body.username, &body.password).await?;
+    let user = match &auth.user_id {
+        Some(user_id) => match User::find_by_id(user_id).await? {
+            Some(user) => user,
+            // registered, but the user write failed; finish it now
+            None => User::create_password(service.id.clone(), &auth).await?,
+        },
+        // registered before users were linked; link on first login
+        None => {
+            let user = User::create_password(service.id.clone(), &auth).await?;
+            auth.link_user(&state.dynamo, &user.id).await?;
+            user
+        }
+    };
+    // unlinked, but deleting the credentials failed; finish that now
+    let linked = user
+        .auth
+        .password
+        .as_ref()
+        .map(|password| password.auth_id.as_str());
+    if linked != Some(auth.id.as_str()) {
+        Auth::delete(&state.dynamo, &auth.id).await?;
+        return Err(AppError::unauthorized("invalid username or password"));
+    }
+    // usernames are global, but a login only signs in to the service it was registered with
+    if user.service != service.id {
+        return Err(AppError::unauthorized("invalid username or password"));
+    }
+    let tokens = user.issue_tokens(&state.env).await?;
+    Ok(Json(json!({ "tokens": tokens, "user": PublicCredentials::from(auth) })).into_response())
 }
 
-pub async fn register(State(state): State<AppState>, Json(body): Json<Login>) -> ApiResponse {
+pub async fn register(
+    State(state): State<AppState>,
+    RequireService(service): RequireService,
+    Json(body): Json<Login>,
+) -> ApiResponse {
     let mut new = Auth {
         username: body.username,
-        password: body.password,
+        password: body.password.into(),
         metadata: None,
+        user_id: Some(User::generate_nanoid()),
         ..Default::default()
     };
     let inserted = new.register(&state.dynamo).await?;
-    Ok(Json(inserted).into_response())
+    // don't leave the username taken by a login with no user behind it
+    if let Err(err) = User::create_password(service.id, &inserted).await {
+        Auth::delete(&state.dynamo, &inserted.id).await?;
+        return Err(err);
+    }
+    Ok(Json(PublicCredentials::from(inserted)).into_response())
+}
+
+pub async fn refresh(
+    State(state): State<AppState>,
+    Json(body): Json<RefreshTokenBody>,
+) -> ApiResponse {
+    let tokens = User::refresh_tokens(&body.refresh_token, &state.env).await?;
+    Ok(Json(tokens).into_response())
+}
+
+pub async fn logout(Json(body): Json<RefreshTokenBody>) -> ApiResponse {
+    let refresh_token = RefreshToken::read_by_id(RefreshToken::hash(&body.refresh_token))
+        .await
+        .map_err(|_| AppError::unauthorized("invalid refresh token"))?;
+    RefreshToken::revoke_family(&refresh_token.family_id).await?;
+    Ok(StatusCode::NO_CONTENT.into_response())
 }
@@ -9,4 +9,6 @@ pub fn router() -> axum::Router<AppState> {
         .route("/:id", get(controller::read_by_id))
         .route("/register", post(controller::register))
         .route("/login", post(controller::login))
+        .route("/refresh", post(controller::refresh))
+        .route("/logout", post(controller::logout))
 }
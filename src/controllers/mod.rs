@@ -1,12 +1,16 @@
 use crate::types::AppState;
 
+mod admin;
 mod auth;
 mod dev;
 mod oauth;
+mod well_known;
 
-pub fn routes() -> axum::Router<AppState> {
+pub fn routes(state: &AppState) -> axum::Router<AppState> {
     axum::Router::new()
         .nest("/dev", dev::router())
         .nest("/auth", auth::router())
         .nest("/oauth", oauth::router())
+        .nest("/admin", admin::router(state))
+        .nest("/.well-known", well_known::router())
 }
@@ -1,58 +1,234 @@
 use crate::{
     errors::AppError,
-    jwt::Service,
+    middleware::{AuthUser, RequireService},
     models::{
+        authorization_code::AuthorizationCode,
         oauth_link_state::{LinkState, Provider},
-        user::User,
+        user::{PublicAccount, User, AVATAR_MAX_BYTES, AVATAR_UPLOAD_TTL},
     },
     oauth::{self},
-    types::{ApiResponse, AppState},
+    service::ClientApp,
+    types::{ApiResponse, AppState, AvatarUpload, AvatarUploadUrl, UpdateProfile},
 };
 use axum::{
-    extract::{Query, Request, State},
-    http::HeaderMap,
-    response::IntoResponse,
+    extract::{Path, Query, State},
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
     Json,
 };
 use mongoose::Model;
-use serde_json::json;
+use reqwest::Url;
 
-pub async fn get_oauth_links(State(state): State<AppState>, headers: HeaderMap) -> ApiResponse {
-    let link_state = LinkState {
-        service: Service::from_headers(headers)?,
-        provider: Provider::GOOGLE,
-        ..Default::default()
+/// one authorize url per provider the service allows; `user_id` makes them link to that account
+async fn build_links(
+    state: &AppState,
+    service: &ClientApp,
+    return_to: Option<String>,
+    user_id: Option<String>,
+) -> Result<oauth::types::Links, AppError> {
+    if let Some(return_to) = &return_to {
+        service.check_redirect(return_to)?;
     }
-    .save()
-    .await
-    .map_err(AppError::bad_request)?;
-    let google = oauth::google::build_oauth_link(&state.env.google_client_id, &link_state).await?;
-    let links = oauth::types::Links { google };
+    let mut links = oauth::types::Links::new();
+    let providers = state
+        .oauth
+        .providers()
+        .filter(|provider| service.allows_provider(&provider.provider()));
+    for provider in providers {
+        let link_state = LinkState {
+            user_id: user_id.clone(),
+            return_to: return_to.clone(),
+            ..LinkState::new(
+                service.id.clone(),
+                provider.provider(),
+                &state.env.public_url,
+            )
+        };
+        // one unreachable provider shouldn't take every other login down with it
+        let link = match provider.authorize_url(&link_state).await {
+            Ok(link) => link,
+            Err(err) => {
+                tracing::error!("[ERROR]: building {} link: {err:?}", provider.provider());
+                continue;
+            }
+        };
+        link_state.save().await.map_err(AppError::bad_request)?;
+        links.insert(provider.provider().slug().to_string(), link);
+    }
+    Ok(links)
+}
+
+pub async fn get_oauth_links(
+    State(state): State<AppState>,
+    RequireService(service): RequireService,
+    Query(query): Query<oauth::types::LinkQuery>,
+) -> ApiResponse {
+    let links = build_links(&state, &service, query.return_to, None).await?;
     Ok(Json(links).into_response())
 }
 
-pub async fn user(State(state): State<AppState>, req: Request) -> ApiResponse {
-    let user = User::authenticate(req, &state.env.jwt_secret).await?;
-    Ok(Json(user).into_response())
+/// links that attach another provider to the signed in user instead of signing in
+pub async fn get_account_links(
+    State(state): State<AppState>,
+    AuthUser(user): AuthUser,
+    Query(query): Query<oauth::types::LinkQuery>,
+) -> ApiResponse {
+    let service = state.env.services.get(&user.service)?;
+    let links = build_links(&state, service, query.return_to, Some(user.id)).await?;
+    Ok(Json(links).into_response())
+}
+
+pub async fn unlink_provider(
+    State(state): State<AppState>,
+    AuthUser(user): AuthUser,
+    Path(provider): Path<String>,
+) -> ApiResponse {
+    let user = User::unlink(&state.dynamo, &user, &provider).await?;
+    Ok(Json(PublicAccount::from(user)).into_response())
+}
+
+pub async fn user(AuthUser(user): AuthUser) -> ApiResponse {
+    Ok(Json(PublicAccount::from(user)).into_response())
+}
+
+pub async fn update_profile(
+    State(state): State<AppState>,
+    AuthUser(user): AuthUser,
+    Json(body): Json<UpdateProfile>,
+) -> ApiResponse {
+    let user = user.update_profile(&state.bucket, body).await?;
+    Ok(Json(PublicAccount::from(user)).into_response())
+}
+
+pub async fn avatar_upload(
+    State(state): State<AppState>,
+    AuthUser(user): AuthUser,
+    Json(body): Json<AvatarUpload>,
+) -> ApiResponse {
+    let (key, upload_url, content_type) = user
+        .avatar_upload_url(&state.bucket, &body.content_type)
+        .await?;
+    Ok(Json(AvatarUploadUrl {
+        key,
+        upload_url,
+        content_type,
+        max_bytes: AVATAR_MAX_BYTES,
+        expires_in: AVATAR_UPLOAD_TTL.as_secs(),
+    })
+    .into_response())
 }
 
-pub async fn google_redirect_handler(
+pub async fn delete_account(
     State(state): State<AppState>,
-    Query(query): Query<oauth::types::GoogleOauthCallback>,
+    AuthUser(user): AuthUser,
 ) -> ApiResponse {
-    // assert link state was created by this service
-    let link = LinkState::read_by_id(&query.state)
+    User::delete_account(&state.dynamo, &state.bucket, &state.oauth, &user.id, None).await?;
+    Ok(StatusCode::NO_CONTENT.into_response())
+}
+
+pub async fn export(State(state): State<AppState>, AuthUser(user): AuthUser) -> ApiResponse {
+    let export = user.export(&state.dynamo, &state.bucket).await?;
+    Ok((
+        [(
+            header::CONTENT_DISPOSITION,
+            format!("attachment; filename=\"{}.json\"", user.id),
+        )],
+        Json(export),
+    )
+        .into_response())
+}
+
+pub async fn revoke_sessions(AuthUser(user): AuthUser) -> ApiResponse {
+    User::revoke_sessions(&user.id).await?;
+    Ok(StatusCode::NO_CONTENT.into_response())
+}
+
+pub async fn redirect_handler(
+    State(state): State<AppState>,
+    Path(callback): Path<String>,
+    Query(query): Query<oauth::types::OAuthCallback>,
+) -> ApiResponse {
+    let provider = callback
+        .strip_suffix("-redirect")
+        .ok_or_else(|| AppError::not_found("route not found"))?
+        .parse::<Provider>()?;
+    // single use, and only for the provider it was issued for
+    let link = LinkState::consume(&query.state, &provider).await?;
+    let service = state.env.services.get(&link.service)?;
+    if !service.allows_provider(&provider) {
+        return Err(AppError::forbidden(format!(
+            "{provider} login is not enabled for {}",
+            service.id
+        )));
+    }
+    let code = match (query.code, query.error) {
+        (Some(code), None) => code,
+        // e.g. the user pressed cancel; browsers go back to the app, not an api error page
+        (_, error) => {
+            let error = oauth_error_code(error.as_deref());
+            let Some(return_to) = &link.return_to else {
+                return Err(AppError::unauthorized(format!(
+                    "{provider} login failed: {error}"
+                )));
+            };
+            let mut url = service.check_redirect(return_to)?;
+            url.query_pairs_mut().append_pair("error", error);
+            return Ok(redirect(&url));
+        }
+    };
+    let provider = state.oauth.get(&provider)?;
+    let token_data = provider.exchange_code(&code, &link).await?;
+    let identity = provider.fetch_user_info(&token_data, &link).await?;
+    let user = match &link.user_id {
+        Some(user_id) => User::link_identity(user_id, identity, token_data).await?,
+        None => User::create_or_update(service.id.clone(), identity, token_data).await?,
+    };
+    // refuse suspended and banned users before anything is handed back
+    user.ensure_active()?;
+    let Some(return_to) = link.return_to else {
+        let tokens = user.issue_tokens(&state.env).await?;
+        return Ok(Json(tokens).into_response());
+    };
+    // browser flow: hand the app a one-time code, never the tokens themselves
+    let mut url = service.check_redirect(&return_to)?;
+    let code = AuthorizationCode::issue(&user.id, &link.service).await?;
+    url.query_pairs_mut().append_pair("code", &code);
+    Ok(redirect(&url))
+}
+
+fn redirect(url: &Url) -> Response {
+    (
+        StatusCode::FOUND,
+        [
+            (header::LOCATION, url.to_string()),
+            (header::CACHE_CONTROL, "no-store".to_string()),
+        ],
+    )
+        .into_response()
+}
+
+/// the provider's RFC 6749 error code, if it looks like one; it is echoed to the app
+fn oauth_error_code(error: Option<&str>) -> &str {
+    match error {
+        Some(error)
+            if !error.is_empty() && error.chars().all(|c| c.is_ascii_lowercase() || c == '_') =>
+        {
+            error
+        }
+        Some(_) => "invalid_request",
+        None => "missing_code",
+    }
+}
+
+pub async fn exchange_code(
+    State(state): State<AppState>,
+    RequireService(service): RequireService,
+    Json(body): Json<oauth::types::CodeExchange>,
+) -> ApiResponse {
+    let code = AuthorizationCode::consume(&body.code, &service.id).await?;
+    let user = User::read_by_id(&code.user_id)
         .await
         .map_err(AppError::unauthorized)?;
-    let token_data = oauth::google::handle_callback(
-        &state.env.google_client_id,
-        &state.env.google_client_secret,
-        &query.code,
-        &link.redirect,
-    )
-    .await?;
-    let user_metadata = oauth::google::fetch_user_info(&token_data.access_token).await?;
-    let user = User::create_or_update_google(link.service, user_metadata, token_data).await?;
-    let token = user.sign_token(&state.env.jwt_secret)?;
-    Ok(Json(json!({ "token": token })).into_response())
+    let tokens = user.issue_tokens(&state.env).await?;
+    Ok(Json(tokens).into_response())
 }
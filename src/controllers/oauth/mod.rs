@@ -1,4 +1,4 @@
-use axum::routing::get;
+use axum::routing::{delete, get, post};
 
 use crate::types::AppState;
 
@@ -7,7 +7,21 @@ mod controller;
 pub fn router() -> axum::Router<AppState> {
     axum::Router::new()
         .route("/", get(controller::get_oauth_links))
-        .route("/me", get(controller::user))
-        // google
-        .route("/google-redirect", get(controller::google_redirect_handler))
+        .route(
+            "/me",
+            get(controller::user)
+                .patch(controller::update_profile)
+                .delete(controller::delete_account),
+        )
+        .route("/me/avatar", post(controller::avatar_upload))
+        .route("/me/export", get(controller::export))
+        .route("/me/revoke-sessions", post(controller::revoke_sessions))
+        .route("/me/links", get(controller::get_account_links))
+        .route(
+            "/me/providers/:provider",
+            delete(controller::unlink_provider),
+        )
+        .route("/token", post(controller::exchange_code))
+        // `/google-redirect`, etc.
+        .route("/:callback", get(controller::redirect_handler))
 }
@@ -0,0 +1,8 @@
+use axum::{extract::State, http::header, response::IntoResponse, Json};
+
+use crate::types::{ApiResponse, AppState};
+
+pub async fn jwks(State(state): State<AppState>) -> ApiResponse {
+    let jwks = state.env.jwt_keys.jwks();
+    Ok(([(header::CACHE_CONTROL, "public, max-age=300")], Json(jwks)).into_response())
+}
@@ -0,0 +1,9 @@
+use axum::routing::get;
+
+use crate::types::AppState;
+
+mod controller;
+
+pub fn router() -> axum::Router<AppState> {
+    axum::Router::new().route("/jwks.json", get(controller::jwks))
+}
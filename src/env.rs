@@ -1,7 +1,17 @@
+use reqwest::Url;
 use serde::{Deserialize, Serialize};
 use tracing::Level;
 
-use crate::errors::AppError;
+use crate::{
+    errors::AppError,
+    jwt::keys::{KeyConfig, KeySet},
+    models::oauth_link_state::Provider,
+    oauth::{
+        github::{GITHUB_API_URL, GITHUB_OAUTH_URL},
+        oidc::types::OidcConfig,
+    },
+    service::{ClientApp, Services},
+};
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub enum Stage {
@@ -11,19 +21,34 @@ pub enum Stage {
     Other(String),
 }
 
+pub const LOCAL_PUBLIC_URL: &str = "http://localhost:3000";
+pub const PROD_PUBLIC_URL: &str = "https://api.pixel-collector.judethings.com";
+
 #[derive(Debug, Clone)]
 pub struct Env {
     pub stage: Stage,
+    pub public_url: String, // base of every absolute url the api hands out, no trailing slash
     pub log_level: Level,
     pub bucket_name: String,
     pub google_client_id: String,
     pub google_client_secret: String,
-    pub jwt_secret: String,
+    pub github_client_id: Option<String>,
+    pub github_client_secret: Option<String>,
+    pub github_oauth_url: String,
+    pub github_api_url: String,
+    pub discord_client_id: Option<String>,
+    pub discord_client_secret: Option<String>,
+    pub oidc_providers: Vec<OidcConfig>,
+    pub services: Services,
+    pub jwt_keys: KeySet,
 }
 
 impl Env {
+    /// `None` when unset or blank; deploys map every secret, so a missing one arrives as `""`
     fn _get_optional_string(key: &str) -> Option<String> {
-        std::env::var(key.trim().to_uppercase()).ok()
+        std::env::var(key.trim().to_uppercase())
+            .ok()
+            .filter(|value| !value.trim().is_empty())
     }
 
     fn _get_required_string(key: &str) -> Result<String, AppError> {
@@ -57,18 +82,116 @@ impl Env {
         }
     }
 
+    /// `PUBLIC_URL` wins; local and prod have defaults, every other stage must set it
+    pub fn public_url(stage: &Stage) -> Result<String, AppError> {
+        let url = match (Self::_get_optional_string("PUBLIC_URL"), stage) {
+            (Some(url), _) => url,
+            (None, Stage::Local) => LOCAL_PUBLIC_URL.to_string(),
+            (None, Stage::Prod) => PROD_PUBLIC_URL.to_string(),
+            (None, _) => {
+                return Err(AppError::env_error(
+                    "PUBLIC_URL must be set outside local and prod",
+                ))
+            }
+        };
+        // redirect uris and the token issuer are built from it, so it must be absolute
+        match Url::parse(&url) {
+            Ok(parsed) if matches!(parsed.scheme(), "http" | "https") && parsed.has_host() => {
+                Ok(url.trim_end_matches('/').to_string())
+            }
+            _ => Err(AppError::env_error(format!(
+                "PUBLIC_URL must be an absolute http(s) url, got {url:?}"
+            ))),
+        }
+    }
+
+    /// client applications from `SERVICES` (json); a lone localhost service when unset
+    pub fn services() -> Result<Services, AppError> {
+        let configs = match Self::_get_optional_string("SERVICES") {
+            Some(json) => {
+                serde_json::from_str::<Vec<ClientApp>>(&json).map_err(AppError::env_error)?
+            }
+            None => vec![ClientApp::localhost()],
+        };
+        Services::new(configs)
+    }
+
+    pub fn jwt_keys() -> Result<KeySet, AppError> {
+        let keys = match Self::_get_optional_string("JWT_SIGNING_KEYS") {
+            Some(json) => {
+                serde_json::from_str::<Vec<KeyConfig>>(&json).map_err(AppError::env_error)?
+            }
+            None => vec![],
+        };
+        KeySet::new(
+            &keys,
+            Self::_get_optional_string("JWT_ACTIVE_KID").as_deref(),
+            Self::_get_optional_string("JWT_SECRET").as_deref(),
+            Self::_get_optional_string("JWT_SECRET_EXPIRES_AT")
+                .map(|value| value.parse::<i64>().map_err(AppError::env_error))
+                .transpose()?,
+        )
+    }
+
+    pub fn oidc_providers() -> Result<Vec<OidcConfig>, AppError> {
+        let Some(json) = Self::_get_optional_string("OIDC_PROVIDERS") else {
+            return Ok(vec![]);
+        };
+        let configs =
+            serde_json::from_str::<Vec<OidcConfig>>(&json).map_err(AppError::env_error)?;
+        for (index, config) in configs.iter().enumerate() {
+            // names end up in routes and mongo field paths
+            let valid = !config.name.is_empty()
+                && config
+                    .name
+                    .chars()
+                    .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_' || c == '-');
+            if !valid {
+                return Err(AppError::env_error(format!(
+                    "invalid oidc provider name: {:?}",
+                    config.name
+                )));
+            }
+            let builtin = Provider::BUILTIN
+                .iter()
+                .any(|provider| provider.slug() == config.name);
+            let duplicate = configs[..index]
+                .iter()
+                .any(|other| other.name == config.name);
+            if builtin || duplicate {
+                return Err(AppError::env_error(format!(
+                    "oidc provider name already in use: {}",
+                    config.name
+                )));
+            }
+        }
+        Ok(configs)
+    }
+
     pub fn load() -> Result<Self, AppError> {
         if cfg!(debug_assertions) {
             use dotenv::dotenv;
             dotenv().ok();
         }
+        let stage = Self::stage()?;
         Ok(Self {
-            stage: Self::stage()?,
+            public_url: Self::public_url(&stage)?,
+            stage,
             log_level: Self::log_level(),
             bucket_name: Self::_get_required_string("BUCKET_NAME")?,
             google_client_id: Self::_get_required_string("GOOGLE_CLIENT_ID")?,
             google_client_secret: Self::_get_required_string("GOOGLE_CLIENT_SECRET")?,
-            jwt_secret: Self::_get_required_string("JWT_SECRET")?,
+            github_client_id: Self::_get_optional_string("GITHUB_CLIENT_ID"),
+            github_client_secret: Self::_get_optional_string("GITHUB_CLIENT_SECRET"),
+            github_oauth_url: Self::_get_optional_string("GITHUB_OAUTH_URL")
+                .unwrap_or_else(|| GITHUB_OAUTH_URL.to_string()),
+            github_api_url: Self::_get_optional_string("GITHUB_API_URL")
+                .unwrap_or_else(|| GITHUB_API_URL.to_string()),
+            discord_client_id: Self::_get_optional_string("DISCORD_CLIENT_ID"),
+            discord_client_secret: Self::_get_optional_string("DISCORD_CLIENT_SECRET"),
+            oidc_providers: Self::oidc_providers()?,
+            services: Self::services()?,
+            jwt_keys: Self::jwt_keys()?,
         })
     }
 }
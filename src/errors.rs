@@ -1,11 +1,75 @@
 use axum::{
-    http::StatusCode,
+    http::{header, StatusCode},
     response::{IntoResponse, Response},
     Json,
 };
 use serde::Serialize;
 use thiserror::Error;
 
+/// why an access token was rejected; `code()` is returned to clients so they know when to refresh
+#[derive(Debug, Error, Clone, Copy, PartialEq, Eq)]
+pub enum TokenError {
+    #[error("token expired")]
+    Expired,
+    #[error("token not yet valid")]
+    NotYetValid,
+    #[error("token signature is invalid")]
+    BadSignature,
+    #[error("token is malformed")]
+    Malformed,
+    #[error("token was issued for another audience")]
+    WrongAudience,
+    #[error("token was issued by another issuer")]
+    WrongIssuer,
+    #[error("missing auth token")]
+    Missing,
+    #[error("auth scheme must be Bearer")]
+    WrongScheme,
+    #[error("token was revoked")]
+    Revoked,
+    #[error("token's user no longer exists")]
+    UnknownUser,
+}
+
+impl TokenError {
+    pub const fn code(self) -> &'static str {
+        match self {
+            Self::Expired => "token_expired",
+            Self::NotYetValid => "token_not_yet_valid",
+            Self::BadSignature => "token_bad_signature",
+            Self::Malformed => "token_malformed",
+            Self::WrongAudience => "token_wrong_audience",
+            Self::WrongIssuer => "token_wrong_issuer",
+            Self::Missing => "token_missing",
+            Self::WrongScheme => "token_wrong_scheme",
+            Self::Revoked => "token_revoked",
+            Self::UnknownUser => "token_unknown_user",
+        }
+    }
+
+    /// the RFC 6750 §3.1 error code; a request without credentials gets none
+    pub const fn error(self) -> Option<&'static str> {
+        match self {
+            Self::Missing => None,
+            Self::WrongScheme => Some("invalid_request"),
+            _ => Some("invalid_token"),
+        }
+    }
+
+    /// the `WWW-Authenticate` challenge for this rejection
+    pub fn challenge(self) -> String {
+        self.error().map_or_else(
+            || format!("Bearer error_code=\"{}\"", self.code()),
+            |error| {
+                format!(
+                    "Bearer error=\"{error}\", error_description=\"{self}\", error_code=\"{}\"",
+                    self.code()
+                )
+            },
+        )
+    }
+}
+
 #[derive(Debug, Error)]
 pub enum AppError {
     #[error("{0}")]
@@ -20,6 +84,16 @@ pub enum AppError {
     BadRequest(String),
     #[error("{0}")]
     NotFound(String),
+    #[error("{0}")]
+    InvalidToken(TokenError),
+    #[error("{message}")]
+    AccountRestricted { code: &'static str, message: String },
+    #[error("oauth state is invalid or was already used")]
+    InvalidOAuthState,
+    #[error("oauth state has expired")]
+    ExpiredOAuthState,
+    #[error("oauth state was issued for {expected}, not {received}")]
+    OAuthProviderMismatch { expected: String, received: String },
 }
 
 #[allow(clippy::needless_pass_by_value)]
@@ -52,21 +126,42 @@ impl AppError {
 #[derive(Serialize)]
 pub struct ErrorMessage {
     pub error: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub code: Option<&'static str>,
 }
 
 impl IntoResponse for AppError {
     fn into_response(self) -> Response {
         let status = match self {
-            Self::Unauthorized(_) => StatusCode::UNAUTHORIZED,
-            Self::Forbidden(_) => StatusCode::FORBIDDEN,
+            Self::Unauthorized(_)
+            | Self::InvalidToken(_)
+            | Self::InvalidOAuthState
+            | Self::ExpiredOAuthState
+            | Self::OAuthProviderMismatch { .. } => StatusCode::UNAUTHORIZED,
+            Self::Forbidden(_) | Self::AccountRestricted { .. } => StatusCode::FORBIDDEN,
             Self::NotFound(_) => StatusCode::NOT_FOUND,
             Self::BadRequest(_) => StatusCode::BAD_REQUEST,
             _ => StatusCode::INTERNAL_SERVER_ERROR,
         };
         tracing::error!("[ERROR]: {self:?}");
+        let code = match &self {
+            Self::InvalidToken(err) => Some(err.code()),
+            Self::AccountRestricted { code, .. } => Some(*code),
+            _ => None,
+        };
         let error = ErrorMessage {
             error: self.to_string(),
+            code,
         };
-        (status, Json(error)).into_response()
+        let mut response = (status, Json(error)).into_response();
+        if let Self::InvalidToken(err) = self {
+            // RFC 6750 §3
+            if let Ok(value) = err.challenge().parse() {
+                response
+                    .headers_mut()
+                    .insert(header::WWW_AUTHENTICATE, value);
+            }
+        }
+        response
     }
 }
@@ -0,0 +1,273 @@
+use base64::{
+    engine::general_purpose::{STANDARD, URL_SAFE_NO_PAD},
+    Engine,
+};
+use core::fmt;
+use jsonwebtoken::{
+    jwk::{
+        AlgorithmParameters, CommonParameters, EllipticCurve, Jwk, JwkSet, KeyAlgorithm,
+        OctetKeyPairParameters, OctetKeyPairType, PublicKeyUse, RSAKeyParameters, RSAKeyType,
+    },
+    Algorithm, DecodingKey, EncodingKey,
+};
+use pkcs1::RsaPublicKey;
+use serde::Deserialize;
+use spki::SubjectPublicKeyInfoRef;
+
+use crate::errors::AppError;
+
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum KeyType {
+    RS256,
+    EdDSA,
+}
+
+impl KeyType {
+    const fn algorithm(self) -> Algorithm {
+        match self {
+            Self::RS256 => Algorithm::RS256,
+            Self::EdDSA => Algorithm::EdDSA,
+        }
+    }
+}
+
+/// one entry of `JWT_SIGNING_KEYS`. only the active key needs a `private_key`;
+/// retired keys keep verifying until `expires_at` (unix seconds) so tokens
+/// signed before a rotation stay valid for the rotation window
+#[derive(Deserialize, Clone)]
+pub struct KeyConfig {
+    pub kid: String,
+    pub alg: KeyType,
+    pub public_key: String,          // SPKI PEM
+    pub private_key: Option<String>, // PKCS#8 PEM
+    pub expires_at: Option<i64>,
+}
+
+#[derive(Clone)]
+struct VerifyingKey {
+    kid: String,
+    alg: KeyType,
+    decoding: DecodingKey,
+    jwk: Jwk,
+    expires_at: Option<i64>,
+}
+
+impl VerifyingKey {
+    fn is_expired(&self) -> bool {
+        self.expires_at
+            .is_some_and(|expires_at| expires_at <= chrono::Utc::now().timestamp())
+    }
+}
+
+#[derive(Clone)]
+struct SigningKey {
+    kid: Option<String>,
+    algorithm: Algorithm,
+    encoding: EncodingKey,
+}
+
+#[derive(Clone)]
+struct LegacySecret {
+    encoding: EncodingKey,
+    decoding: DecodingKey,
+    expires_at: Option<i64>, // unix seconds; only `None` while it is still the signing key
+}
+
+impl LegacySecret {
+    fn is_expired(&self) -> bool {
+        self.expires_at
+            .is_some_and(|expires_at| expires_at <= chrono::Utc::now().timestamp())
+    }
+}
+
+/// every key the api signs or verifies access tokens with
+#[derive(Clone)]
+pub struct KeySet {
+    signing: SigningKey,
+    verifying: Vec<VerifyingKey>,
+    // HS512 `JWT_SECRET`, accepted for tokens without a `kid`. once signing keys are set
+    // it only verifies until `JWT_SECRET_EXPIRES_AT`, and not at all without one
+    legacy: Option<LegacySecret>,
+}
+
+impl fmt::Debug for KeySet {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("KeySet")
+            .field("signing", &self.signing.kid)
+            .field(
+                "verifying",
+                &self
+                    .verifying
+                    .iter()
+                    .map(|key| &key.kid)
+                    .collect::<Vec<_>>(),
+            )
+            .field("legacy", &self.legacy.is_some())
+            .finish()
+    }
+}
+
+impl KeySet {
+    pub fn new(
+        keys: &[KeyConfig],
+        active_kid: Option<&str>,
+        legacy_secret: Option<&str>,
+        legacy_expires_at: Option<i64>,
+    ) -> Result<Self, AppError> {
+        let legacy = legacy_secret.map(|secret| LegacySecret {
+            encoding: EncodingKey::from_secret(secret.as_ref()),
+            decoding: DecodingKey::from_secret(secret.as_ref()),
+            expires_at: None,
+        });
+        let verifying = keys
+            .iter()
+            .map(Self::verifying_key)
+            .collect::<Result<Vec<_>, _>>()?;
+        if keys.is_empty() {
+            let Some(legacy) = legacy else {
+                return Err(AppError::env_error(
+                    "one of JWT_SIGNING_KEYS or JWT_SECRET is required",
+                ));
+            };
+            return Ok(Self {
+                signing: SigningKey {
+                    kid: None,
+                    algorithm: Algorithm::HS512,
+                    encoding: legacy.encoding.clone(),
+                },
+                verifying,
+                legacy: Some(legacy),
+            });
+        }
+        let active_kid =
+            active_kid.ok_or_else(|| AppError::env_error("JWT_ACTIVE_KID is required"))?;
+        let active = keys
+            .iter()
+            .find(|key| key.kid == active_kid)
+            .ok_or_else(|| AppError::env_error(format!("no signing key with kid {active_kid}")))?;
+        let private_key = active.private_key.as_ref().ok_or_else(|| {
+            AppError::env_error(format!("signing key {active_kid} has no private_key"))
+        })?;
+        let encoding = match active.alg {
+            KeyType::RS256 => EncodingKey::from_rsa_pem(private_key.as_bytes()),
+            KeyType::EdDSA => EncodingKey::from_ed_pem(private_key.as_bytes()),
+        }
+        .map_err(AppError::env_error)?;
+        // anyone holding the shared secret could mint tokens forever otherwise
+        let legacy = match (legacy, legacy_expires_at) {
+            (Some(legacy), Some(expires_at)) => Some(LegacySecret {
+                expires_at: Some(expires_at),
+                ..legacy
+            }),
+            (Some(_), None) => {
+                tracing::warn!(
+                    "JWT_SECRET is ignored once JWT_SIGNING_KEYS is set; set JWT_SECRET_EXPIRES_AT to accept its tokens during the migration"
+                );
+                None
+            }
+            (None, _) => None,
+        };
+        Ok(Self {
+            signing: SigningKey {
+                kid: Some(active.kid.to_string()),
+                algorithm: active.alg.algorithm(),
+                encoding,
+            },
+            verifying,
+            legacy,
+        })
+    }
+
+    fn verifying_key(config: &KeyConfig) -> Result<VerifyingKey, AppError> {
+        let pem = config.public_key.as_bytes();
+        let der = Self::pem_to_der(&config.public_key)?;
+        let spki =
+            SubjectPublicKeyInfoRef::try_from(der.as_slice()).map_err(AppError::env_error)?;
+        let subject_public_key = spki.subject_public_key.raw_bytes();
+        let (decoding, algorithm) = match config.alg {
+            KeyType::RS256 => {
+                let public_key =
+                    RsaPublicKey::try_from(subject_public_key).map_err(AppError::env_error)?;
+                let parameters = AlgorithmParameters::RSA(RSAKeyParameters {
+                    key_type: RSAKeyType::RSA,
+                    n: URL_SAFE_NO_PAD.encode(public_key.modulus.as_bytes()),
+                    e: URL_SAFE_NO_PAD.encode(public_key.public_exponent.as_bytes()),
+                });
+                (DecodingKey::from_rsa_pem(pem), parameters)
+            }
+            KeyType::EdDSA => {
+                if subject_public_key.len() != 32 {
+                    return Err(AppError::env_error("invalid Ed25519 public key"));
+                }
+                let parameters = AlgorithmParameters::OctetKeyPair(OctetKeyPairParameters {
+                    key_type: OctetKeyPairType::OctetKeyPair,
+                    curve: EllipticCurve::Ed25519,
+                    x: URL_SAFE_NO_PAD.encode(subject_public_key),
+                });
+                (DecodingKey::from_ed_pem(pem), parameters)
+            }
+        };
+        let jwk = Jwk {
+            common: CommonParameters {
+                public_key_use: Some(PublicKeyUse::Signature),
+                key_algorithm: Some(match config.alg {
+                    KeyType::RS256 => KeyAlgorithm::RS256,
+                    KeyType::EdDSA => KeyAlgorithm::EdDSA,
+                }),
+                key_id: Some(config.kid.to_string()),
+                ..Default::default()
+            },
+            algorithm,
+        };
+        Ok(VerifyingKey {
+            kid: config.kid.to_string(),
+            alg: config.alg,
+            decoding: decoding.map_err(AppError::env_error)?,
+            jwk,
+            expires_at: config.expires_at,
+        })
+    }
+
+    fn pem_to_der(pem: &str) -> Result<Vec<u8>, AppError> {
+        let body = pem
+            .lines()
+            .filter(|line| !line.starts_with("-----"))
+            .collect::<String>();
+        STANDARD.decode(body.trim()).map_err(AppError::env_error)
+    }
+
+    pub(super) fn encoding(&self) -> (Option<&str>, Algorithm, &EncodingKey) {
+        (
+            self.signing.kid.as_deref(),
+            self.signing.algorithm,
+            &self.signing.encoding,
+        )
+    }
+
+    /// finds the key a token claims to be signed with; retired keys past their window are ignored
+    pub(super) fn decoding(&self, kid: Option<&str>) -> Option<(Algorithm, &DecodingKey)> {
+        let Some(kid) = kid else {
+            return self
+                .legacy
+                .as_ref()
+                .filter(|legacy| !legacy.is_expired())
+                .map(|legacy| (Algorithm::HS512, &legacy.decoding));
+        };
+        self.verifying
+            .iter()
+            .find(|key| key.kid == kid && !key.is_expired())
+            .map(|key| (key.alg.algorithm(), &key.decoding))
+    }
+
+    /// public half of every key still accepted, as served from `/.well-known/jwks.json`
+    pub fn jwks(&self) -> JwkSet {
+        JwkSet {
+            keys: self
+                .verifying
+                .iter()
+                .filter(|key| !key.is_expired())
+                .map(|key| key.jwk.clone())
+                .collect(),
+        }
+    }
+}
@@ -0,0 +1,151 @@
+use axum::http::{header, HeaderMap};
+use chrono::Duration;
+use jsonwebtoken::{decode, decode_header, encode, errors::ErrorKind, Header, Validation};
+use keys::KeySet;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    errors::{AppError, TokenError},
+    rbac::{Permission, Role},
+    service::Service,
+};
+
+pub mod keys;
+
+/// registered claims (RFC 7519 §4.1) plus the user's `token_version`
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Claims {
+    pub iss: String,  // this api's public url
+    pub sub: String,  // user id
+    pub aud: Service, // the application the token was minted for
+    pub jti: String,  // unique per token
+    pub exp: i64,     // expiration time (as UTC timestamp)
+    pub nbf: i64,     // not before (as UTC timestamp)
+    pub iat: i64,     // issued at (as UTC timestamp)
+    pub token_version: u32,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub roles: Vec<Role>,
+    // effective permissions, for services that only see the token
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub permissions: Vec<Permission>,
+}
+
+impl Claims {
+    pub fn new(
+        issuer: &str,
+        user_id: &str,
+        audience: &Service,
+        token_version: u32,
+        ttl: Duration,
+    ) -> Self {
+        let iat = chrono::Utc::now();
+        Self {
+            iss: issuer.to_string(),
+            sub: user_id.to_string(),
+            aud: audience.clone(),
+            jti: nanoid::nanoid!(),
+            exp: (iat + ttl).timestamp(),
+            nbf: iat.timestamp(),
+            iat: iat.timestamp(),
+            token_version,
+            roles: vec![],
+            permissions: vec![],
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct TokenPair {
+    pub token_type: &'static str,
+    pub access_token: String,
+    pub expires_in: i64, // seconds
+    pub refresh_token: String,
+}
+
+impl TokenPair {
+    pub fn bearer(access_token: String, expires_in: Duration, refresh_token: String) -> Self {
+        Self {
+            token_type: "Bearer",
+            access_token,
+            expires_in: expires_in.num_seconds(),
+            refresh_token,
+        }
+    }
+}
+
+pub fn sign(claims: &Claims, keys: &KeySet) -> Result<String, AppError> {
+    let (kid, algorithm, encoding_key) = keys.encoding();
+    let header = Header {
+        kid: kid.map(ToString::to_string),
+        ..Header::new(algorithm)
+    };
+    encode(&header, claims, encoding_key).map_err(AppError::internal_server_error)
+}
+
+/// the token from `Authorization: Bearer <token>`; the scheme is case-insensitive
+/// and surrounding whitespace is ignored (RFC 6750 §2.1, RFC 9110 §11.1)
+pub fn bearer_token(headers: &HeaderMap) -> Result<&str, AppError> {
+    let value = headers
+        .get(header::AUTHORIZATION)
+        .ok_or(AppError::InvalidToken(TokenError::Missing))?
+        .to_str()
+        .map_err(|_| AppError::InvalidToken(TokenError::Malformed))?
+        .trim();
+    if value.is_empty() {
+        return Err(AppError::InvalidToken(TokenError::Missing));
+    }
+    let (scheme, token) = value
+        .split_once(|c: char| c.is_ascii_whitespace())
+        .unwrap_or((value, ""));
+    if !scheme.eq_ignore_ascii_case("bearer") {
+        return Err(AppError::InvalidToken(TokenError::WrongScheme));
+    }
+    let token = token.trim();
+    if token.is_empty() {
+        return Err(AppError::InvalidToken(TokenError::Missing));
+    }
+    if token.contains(|c: char| c.is_ascii_whitespace()) {
+        return Err(AppError::InvalidToken(TokenError::Malformed));
+    }
+    Ok(token)
+}
+
+/// checks signature, expiry, and that the token was minted by `issuer` for `audience`
+pub fn verify(
+    token: &str,
+    keys: &KeySet,
+    issuer: &str,
+    audience: &Service,
+) -> Result<Claims, AppError> {
+    let header = decode_header(token).map_err(token_error)?;
+    // a kid we never issued (or have retired) can't carry a valid signature
+    let (algorithm, decoding_key) = keys
+        .decoding(header.kid.as_deref())
+        .ok_or(AppError::InvalidToken(TokenError::BadSignature))?;
+    let mut validation = Validation::new(algorithm);
+    validation.set_issuer(&[issuer]);
+    validation.set_audience(&[audience.as_str()]);
+    validation.set_required_spec_claims(&["exp", "nbf", "iss", "aud", "sub"]);
+    validation.validate_nbf = true;
+    let data = decode::<Claims>(token, decoding_key, &validation).map_err(token_error)?;
+    Ok(data.claims)
+}
+
+#[allow(clippy::needless_pass_by_value)]
+fn token_error(err: jsonwebtoken::errors::Error) -> AppError {
+    let kind = match err.kind() {
+        ErrorKind::ExpiredSignature => TokenError::Expired,
+        ErrorKind::ImmatureSignature => TokenError::NotYetValid,
+        ErrorKind::InvalidSignature | ErrorKind::InvalidAlgorithm => TokenError::BadSignature,
+        ErrorKind::InvalidAudience => TokenError::WrongAudience,
+        ErrorKind::InvalidIssuer => TokenError::WrongIssuer,
+        // our own keys failing is a server problem, not the client's
+        ErrorKind::InvalidEcdsaKey
+        | ErrorKind::InvalidRsaKey(_)
+        | ErrorKind::RsaFailedSigning
+        | ErrorKind::InvalidKeyFormat
+        | ErrorKind::Crypto(_) => return AppError::internal_server_error(err),
+        _ => TokenError::Malformed,
+    };
+    AppError::InvalidToken(kind)
+}
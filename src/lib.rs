@@ -4,10 +4,19 @@ pub mod controllers;
 pub mod env;
 pub mod errors;
 pub mod jwt;
+pub mod middleware;
 pub mod models;
 pub mod oauth;
+pub mod password;
+pub mod rbac;
+pub mod secret;
+pub mod service;
 pub mod types;
 
+// tests
+#[cfg(test)]
+mod tests;
+
 pub mod logger {
     use tracing_subscriber::FmtSubscriber;
 
@@ -0,0 +1,97 @@
+use axum::{
+    async_trait,
+    extract::{FromRequestParts, Request, State},
+    http::{header, request::Parts},
+    middleware::Next,
+    response::Response,
+};
+
+use crate::{
+    errors::AppError, models::user::User, rbac::Permission, service::ClientApp, types::AppState,
+};
+
+/// the user behind the request's bearer token; rejects with 401 when there isn't one
+#[derive(Debug, Clone)]
+pub struct AuthUser(pub User);
+
+#[async_trait]
+impl FromRequestParts<AppState> for AuthUser {
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &AppState) -> Result<Self, AppError> {
+        // already resolved by `require_auth`
+        if let Some(user) = parts.extensions.get::<Self>() {
+            return Ok(user.clone());
+        }
+        let user = User::authenticate(&parts.headers, &state.env).await?;
+        Ok(Self(user))
+    }
+}
+
+/// like `AuthUser`, but `None` when no `Authorization` header is sent.
+/// a header that is sent and invalid is still rejected
+#[derive(Debug, Clone)]
+pub struct OptionalAuthUser(pub Option<User>);
+
+#[async_trait]
+impl FromRequestParts<AppState> for OptionalAuthUser {
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &AppState) -> Result<Self, AppError> {
+        if !parts.headers.contains_key(header::AUTHORIZATION) {
+            return Ok(Self(None));
+        }
+        let AuthUser(user) = AuthUser::from_request_parts(parts, state).await?;
+        Ok(Self(Some(user)))
+    }
+}
+
+/// the calling application, from `X-JUDETHING-SERVICE`
+#[derive(Debug, Clone)]
+pub struct RequireService(pub ClientApp);
+
+#[async_trait]
+impl FromRequestParts<AppState> for RequireService {
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &AppState) -> Result<Self, AppError> {
+        let service = state.env.services.from_headers(&parts.headers)?;
+        Ok(Self(service.clone()))
+    }
+}
+
+/// route layer that rejects unauthenticated requests for a whole router:
+/// `.route_layer(axum::middleware::from_fn_with_state(state, require_auth))`.
+/// handlers behind it can take `AuthUser` without a second lookup
+pub async fn require_auth(
+    State(state): State<AppState>,
+    req: Request,
+    next: Next,
+) -> Result<Response, AppError> {
+    let (mut parts, body) = req.into_parts();
+    let user = AuthUser::from_request_parts(&mut parts, &state).await?;
+    parts.extensions.insert(user);
+    Ok(next.run(Request::from_parts(parts, body)).await)
+}
+
+/// what a router requires, as the state of `require_permissions`
+#[derive(Debug, Clone, Copy)]
+pub struct RequirePermissions(pub &'static [Permission]);
+
+/// route layer that checks the authenticated user holds every permission a router declares:
+/// `.route_layer(from_fn_with_state(RequirePermissions(&[Permission::ReadUsers]), require_permissions))`.
+/// must sit inside `require_auth`
+pub async fn require_permissions(
+    State(RequirePermissions(required)): State<RequirePermissions>,
+    req: Request,
+    next: Next,
+) -> Result<Response, AppError> {
+    let AuthUser(user) = req
+        .extensions()
+        .get::<AuthUser>()
+        .ok_or_else(|| AppError::internal_server_error("require_permissions needs require_auth"))?;
+    if !user.has_permissions(required) {
+        return Err(AppError::forbidden("missing required permissions"));
+    }
+    Ok(next.run(req).await)
+}
@@ -3,7 +3,7 @@ use chrono::Utc;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
-use crate::{aws::dynamo::Table, errors::AppError};
+use crate::{aws::dynamo::Table, errors::AppError, password, secret::Secret};
 
 pub const USERNAME_IDX: &str = "username_idx";
 
@@ -11,12 +11,36 @@ pub const USERNAME_IDX: &str = "username_idx";
 pub struct Auth {
     pub id: String,
     pub username: String,
-    pub password: String,
+    #[serde(serialize_with = "crate::secret::serialize")]
+    pub password: Secret<String>,
     pub metadata: Option<Value>,
+    #[serde(default)]
+    pub user_id: Option<String>, // linked mongo `User`, the identity tokens are issued for
     pub created_at: i64,
     pub updated_at: i64,
 }
 
+#[derive(Debug, Serialize, Clone)]
+pub struct PublicCredentials {
+    pub id: String,
+    pub username: String,
+    pub user_id: Option<String>,
+    pub created_at: i64,
+    pub updated_at: i64,
+}
+
+impl From<Auth> for PublicCredentials {
+    fn from(auth: Auth) -> Self {
+        Self {
+            id: auth.id,
+            username: auth.username,
+            user_id: auth.user_id,
+            created_at: auth.created_at,
+            updated_at: auth.updated_at,
+        }
+    }
+}
+
 impl Table for Auth {}
 
 impl Default for Auth {
@@ -25,8 +49,9 @@ impl Default for Auth {
         Self {
             id: Self::generate_nanoid(),
             username: String::default(),
-            password: String::default(),
+            password: Secret::default(),
             metadata: None,
+            user_id: None,
             created_at: now.timestamp_millis(),
             updated_at: now.timestamp_millis(),
         }
@@ -64,8 +89,7 @@ impl Auth {
         if !output.items().is_empty() {
             return Err(AppError::bad_request("username taken"));
         }
-        // hash password
-        self.password = "HASHED".to_string();
+        self.password = password::hash(self.password.expose())?.into();
         let item = self.to_attribute_map()?;
         conn.put_item()
             .table_name(Self::table_name())
@@ -76,15 +100,71 @@ impl Auth {
         Ok(self.clone())
     }
 
-    pub async fn login(conn: &Client, username: &str) -> Result<Self, AppError> {
+    async fn upgrade_password_hash(
+        &mut self,
+        conn: &Client,
+        password: &str,
+    ) -> Result<(), AppError> {
+        let hashed = password::hash(password)?;
+        let now = Utc::now().timestamp_millis();
+        conn.update_item()
+            .table_name(Self::table_name())
+            .key("id", AttributeValue::S(self.id.to_string()))
+            .update_expression("SET #password = :password, #updated_at = :updated_at")
+            .expression_attribute_names("#password", "password")
+            .expression_attribute_names("#updated_at", "updated_at")
+            .expression_attribute_values(":password", AttributeValue::S(hashed.to_string()))
+            .expression_attribute_values(":updated_at", AttributeValue::N(now.to_string()))
+            .send()
+            .await
+            .map_err(AppError::internal_server_error)?;
+        self.password = hashed.into();
+        self.updated_at = now;
+        Ok(())
+    }
+
+    pub async fn link_user(&mut self, conn: &Client, user_id: &str) -> Result<(), AppError> {
+        let now = Utc::now().timestamp_millis();
+        conn.update_item()
+            .table_name(Self::table_name())
+            .key("id", AttributeValue::S(self.id.to_string()))
+            .update_expression("SET #user_id = :user_id, #updated_at = :updated_at")
+            .expression_attribute_names("#user_id", "user_id")
+            .expression_attribute_names("#updated_at", "updated_at")
+            .expression_attribute_values(":user_id", AttributeValue::S(user_id.to_string()))
+            .expression_attribute_values(":updated_at", AttributeValue::N(now.to_string()))
+            .send()
+            .await
+            .map_err(AppError::internal_server_error)?;
+        self.user_id = Some(user_id.to_string());
+        self.updated_at = now;
+        Ok(())
+    }
+
+    pub async fn delete(conn: &Client, id: &str) -> Result<(), AppError> {
+        conn.delete_item()
+            .table_name(Self::table_name())
+            .key("id", AttributeValue::S(id.to_string()))
+            .send()
+            .await
+            .map_err(AppError::internal_server_error)?;
+        Ok(())
+    }
+
+    pub async fn login(conn: &Client, username: &str, password: &str) -> Result<Self, AppError> {
         let output = Self::get_by_username_query(conn, username).await?;
-        let Some(items) = output.items else {
-            return Err(AppError::not_found("username not found"));
+        let Some(first) = output.items().first() else {
+            password::verify_dummy(password);
+            return Err(AppError::unauthorized("invalid username or password"));
         };
-        if let Some(first) = items.first() {
-            // TODO: compare password hash
-            return Self::from_attribute_map(first);
+        let mut auth: Self = Self::from_attribute_map(first)?;
+        password::check(password, auth.password.expose())?;
+        if password::needs_rehash(auth.password.expose()) {
+            // a failed upgrade shouldn't block a valid login; retried next time
+            if let Err(err) = auth.upgrade_password_hash(conn, password).await {
+                tracing::error!("[ERROR]: upgrading password hash for {}: {err:?}", auth.id);
+            }
         }
-        Err(AppError::not_found("username not found"))
+        Ok(auth)
     }
 }
@@ -0,0 +1,88 @@
+use mongoose::{doc, types::MongooseError, DateTime, IndexModel, IndexOptions, Model};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+use crate::{errors::AppError, models::refresh_token::RefreshToken, service::Service};
+
+pub const AUTHORIZATION_CODE_TTL: Duration = Duration::from_secs(60);
+
+/// one-time code handed to an app after the oauth callback, exchanged for tokens
+/// with `POST /oauth/token`. only the sha256 of the code is stored
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct AuthorizationCode {
+    #[serde(rename = "_id")]
+    pub id: String,
+    pub user_id: String,
+    pub service: Service,
+    pub expires_at: DateTime,
+    pub created_at: DateTime,
+    pub updated_at: DateTime,
+}
+
+impl AuthorizationCode {
+    pub async fn migrate() -> Result<Vec<String>, MongooseError> {
+        let created = Self::create_indexes(&[IndexModel::builder()
+            .keys(doc! { "expires_at": 1 })
+            .options(
+                IndexOptions::builder()
+                    .expire_after(Duration::from_secs(0))
+                    .build(),
+            )
+            .build()])
+        .await?;
+        Ok(created.index_names)
+    }
+
+    /// stores a new code and returns the raw value
+    pub async fn issue(user_id: &str, service: &Service) -> Result<String, AppError> {
+        let code = nanoid::nanoid!(48);
+        let now = DateTime::now();
+        let ttl = i64::try_from(AUTHORIZATION_CODE_TTL.as_millis()).unwrap_or(i64::MAX);
+        Self {
+            id: RefreshToken::hash(&code),
+            user_id: user_id.to_string(),
+            service: service.clone(),
+            expires_at: DateTime::from_millis(now.timestamp_millis() + ttl),
+            created_at: now,
+            updated_at: now,
+        }
+        .save()
+        .await
+        .map_err(AppError::internal_server_error)?;
+        Ok(code)
+    }
+
+    /// deletes and returns the code in one step, so it can only be exchanged once
+    pub async fn consume(code: &str, service: &Service) -> Result<Self, AppError> {
+        let authorization_code = Self::collection()
+            .await
+            .find_one_and_delete(doc! { "_id": RefreshToken::hash(code) }, None)
+            .await
+            .map_err(AppError::internal_server_error)?
+            .ok_or_else(|| AppError::unauthorized("invalid authorization code"))?;
+        if authorization_code.expires_at < DateTime::now() {
+            return Err(AppError::unauthorized("authorization code expired"));
+        }
+        if &authorization_code.service != service {
+            return Err(AppError::unauthorized(
+                "authorization code was issued to another service",
+            ));
+        }
+        Ok(authorization_code)
+    }
+}
+
+impl Default for AuthorizationCode {
+    fn default() -> Self {
+        Self {
+            id: Self::generate_nanoid(),
+            user_id: String::default(),
+            service: Service::default(),
+            expires_at: DateTime::now(),
+            created_at: DateTime::now(),
+            updated_at: DateTime::now(),
+        }
+    }
+}
+
+impl Model for AuthorizationCode {}
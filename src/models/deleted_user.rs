@@ -0,0 +1,81 @@
+use mongoose::{doc, types::MongooseError, DateTime, IndexModel, Model};
+use serde::{Deserialize, Serialize};
+
+use crate::{errors::AppError, models::user::User, service::Service};
+
+/// tombstone left when an account is deleted: enough to audit the deletion, nothing personal
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct DeletedUser {
+    #[serde(rename = "_id")]
+    pub id: String, // id the user had
+    pub service: Service,
+    pub login_methods: Vec<String>, // e.g. `google`, `password`
+    pub actor_id: Option<String>,   // admin who deleted the account; `None` when self-service
+    pub deleted_objects: usize,     // s3 objects removed with it
+    pub account_created_at: DateTime,
+    pub created_at: DateTime,
+    pub updated_at: DateTime,
+}
+
+impl DeletedUser {
+    pub async fn migrate() -> Result<Vec<String>, MongooseError> {
+        let created = Self::create_indexes(&[IndexModel::builder()
+            .keys(doc! { "service": 1, "created_at": -1 })
+            .build()])
+        .await?;
+        Ok(created.index_names)
+    }
+
+    /// safe to call again when a deletion is retried: the first tombstone is kept
+    /// and the objects removed on the retry are added to it
+    pub async fn record(
+        user: &User,
+        actor_id: Option<&str>,
+        deleted_objects: usize,
+    ) -> Result<Self, AppError> {
+        let existing = Self::collection()
+            .await
+            .find_one(doc! { "_id": &user.id }, None)
+            .await
+            .map_err(AppError::internal_server_error)?;
+        if existing.is_some() {
+            let deleted_objects =
+                i64::try_from(deleted_objects).map_err(AppError::internal_server_error)?;
+            return Self::update(
+                doc! { "_id": &user.id },
+                doc! { "$inc": { "deleted_objects": deleted_objects } },
+            )
+            .await
+            .map_err(AppError::internal_server_error);
+        }
+        Self {
+            id: user.id.to_string(),
+            service: user.service.clone(),
+            login_methods: user.auth.login_methods(),
+            actor_id: actor_id.map(ToString::to_string),
+            deleted_objects,
+            account_created_at: user.created_at,
+            ..Default::default()
+        }
+        .save()
+        .await
+        .map_err(AppError::internal_server_error)
+    }
+}
+
+impl Default for DeletedUser {
+    fn default() -> Self {
+        Self {
+            id: Self::generate_nanoid(),
+            service: Service::default(),
+            login_methods: vec![],
+            actor_id: None,
+            deleted_objects: 0,
+            account_created_at: DateTime::now(),
+            created_at: DateTime::now(),
+            updated_at: DateTime::now(),
+        }
+    }
+}
+
+impl Model for DeletedUser {}
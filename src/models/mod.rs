@@ -1,3 +1,7 @@
 pub mod auth;
+pub mod authorization_code;
+pub mod deleted_user;
 pub mod oauth_link_state;
+pub mod refresh_token;
 pub mod user;
+pub mod user_status_event;
@@ -1,18 +1,65 @@
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
 use mongoose::{doc, types::MongooseError, DateTime, IndexModel, IndexOptions, Model};
 use serde::{Deserialize, Serialize};
-use std::time::Duration;
+use sha2::{Digest, Sha256};
+use std::{fmt, str::FromStr, time::Duration};
 
-use crate::jwt::Service;
+use crate::{errors::AppError, secret::Secret, service::Service};
 
-#[derive(Debug, Deserialize, Serialize, Clone)]
+pub const LINK_STATE_TTL: Duration = Duration::from_secs(60 * 10);
+
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Eq, Hash)]
 pub enum Provider {
     GOOGLE,
+    GITHUB,
+    DISCORD,
+    OIDC(String), // configured in `OIDC_PROVIDERS`, by name
 }
 
 impl Provider {
-    pub fn to_string(&self) -> String {
+    pub const BUILTIN: [Self; 3] = [Self::GOOGLE, Self::GITHUB, Self::DISCORD];
+
+    /// lowercase name used in routes and link maps, e.g. `/oauth/google-redirect`
+    pub fn slug(&self) -> &str {
+        match self {
+            Self::GOOGLE => "google",
+            Self::GITHUB => "github",
+            Self::DISCORD => "discord",
+            Self::OIDC(name) => name,
+        }
+    }
+
+    /// path of the provider's subdocument under `User.auth`
+    pub fn auth_key(&self) -> String {
         match self {
-            Self::GOOGLE => "GOOGLE".to_string(),
+            Self::OIDC(name) => format!("oidc.{name}"),
+            other => other.slug().to_string(),
+        }
+    }
+}
+
+impl FromStr for Provider {
+    type Err = AppError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_lowercase().as_str() {
+            "google" => Ok(Self::GOOGLE),
+            "github" => Ok(Self::GITHUB),
+            "discord" => Ok(Self::DISCORD),
+            "" => Err(AppError::not_found("unknown provider")),
+            // whether it is configured is up to the registry
+            other => Ok(Self::OIDC(other.to_string())),
+        }
+    }
+}
+
+impl fmt::Display for Provider {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::GOOGLE => write!(f, "GOOGLE"),
+            Self::GITHUB => write!(f, "GITHUB"),
+            Self::DISCORD => write!(f, "DISCORD"),
+            Self::OIDC(name) => write!(f, "{name}"),
         }
     }
 }
@@ -24,33 +71,82 @@ pub struct LinkState {
     pub provider: Provider,
     pub service: Service,
     pub redirect: String,
+    // PKCE (RFC 7636) verifier; only its S256 challenge leaves the server before the code exchange
+    #[serde(default, serialize_with = "crate::secret::serialize")]
+    pub code_verifier: Secret<String>,
+    // set when a signed in user is linking another provider to their account
+    #[serde(default)]
+    pub user_id: Option<String>,
+    // app url the callback redirects to, already checked against the service allowlist
+    #[serde(default)]
+    pub return_to: Option<String>,
+    // echoed back in the provider's id_token, if it issues one
+    #[serde(default)]
+    pub nonce: Option<String>,
     pub created_at: DateTime,
     pub updated_at: DateTime,
 }
 
 impl LinkState {
     pub async fn migrate() -> Result<Vec<String>, MongooseError> {
-        let expiration = Duration::from_secs(60 * 10);
         let created = Self::create_indexes(&[IndexModel::builder()
             .keys(doc! { "created_at": 1 })
-            .options(IndexOptions::builder().expire_after(expiration).build())
+            .options(IndexOptions::builder().expire_after(LINK_STATE_TTL).build())
             .build()])
         .await?;
         Ok(created.index_names)
     }
+
+    /// deletes and returns the state in one step, so a callback can only ever be completed once
+    pub async fn consume(id: &str, provider: &Provider) -> Result<Self, AppError> {
+        let link = Self::collection()
+            .await
+            .find_one_and_delete(doc! { "_id": id }, None)
+            .await
+            .map_err(AppError::internal_server_error)?
+            .ok_or(AppError::InvalidOAuthState)?;
+        if &link.provider != provider {
+            return Err(AppError::OAuthProviderMismatch {
+                expected: link.provider.to_string(),
+                received: provider.to_string(),
+            });
+        }
+        // mongo can take a minute to delete a login abandoned past LINK_STATE_TTL
+        let ttl = i64::try_from(LINK_STATE_TTL.as_millis()).unwrap_or(i64::MAX);
+        if link.created_at.timestamp_millis() + ttl < DateTime::now().timestamp_millis() {
+            return Err(AppError::ExpiredOAuthState);
+        }
+        Ok(link)
+    }
+
+    /// `BASE64URL(SHA256(code_verifier))`, sent with `code_challenge_method=S256`
+    pub fn code_challenge(&self) -> String {
+        URL_SAFE_NO_PAD.encode(Sha256::digest(self.code_verifier.expose().as_bytes()))
+    }
+
+    /// `public_url` is `Env::public_url`, the base the provider sends the user back to
+    pub fn new(service: Service, provider: Provider, public_url: &str) -> Self {
+        Self {
+            service,
+            redirect: format!("{public_url}/oauth/{}-redirect", provider.slug()),
+            code_verifier: Secret::new(nanoid::nanoid!(64)),
+            nonce: Some(nanoid::nanoid!(32)),
+            provider,
+            ..Default::default()
+        }
+    }
 }
 
 impl Default for LinkState {
     fn default() -> Self {
-        let redirect = if cfg!(debug_assertions) {
-            "http://localhost:3000/oauth/google-redirect"
-        } else {
-            "https://api.pixel-collector.judethings.com/oauth/google-redirect"
-        };
         Self {
             id: Self::generate_nanoid(),
-            redirect: redirect.to_string(),
-            service: Service::LOCALHOST,
+            redirect: String::default(),
+            code_verifier: Secret::default(),
+            user_id: None,
+            return_to: None,
+            nonce: None,
+            service: Service::default(),
             provider: Provider::GOOGLE,
             created_at: DateTime::now(),
             updated_at: DateTime::now(),
@@ -0,0 +1,172 @@
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use mongoose::{doc, types::MongooseError, DateTime, IndexModel, IndexOptions, Model};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::time::Duration;
+
+use crate::{errors::AppError, service::Service};
+
+/// opaque, server side refresh token. only the sha256 of the token is stored;
+/// every token issued from the same login shares a `family_id`
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct RefreshToken {
+    #[serde(rename = "_id")]
+    pub id: String,
+    pub user_id: String,
+    pub family_id: String,
+    pub service: Service,
+    pub token_version: u32,
+    pub used_at: Option<DateTime>, // set once rotated; seeing it again means reuse
+    pub expires_at: DateTime,
+    pub created_at: DateTime,
+    pub updated_at: DateTime,
+}
+
+/// a session as the user may see it; never the token hash
+#[derive(Debug, Serialize, Clone)]
+pub struct PublicSession {
+    pub family_id: String,
+    pub service: Service,
+    pub used_at: Option<DateTime>,
+    pub expires_at: DateTime,
+    pub created_at: DateTime,
+}
+
+impl From<RefreshToken> for PublicSession {
+    fn from(token: RefreshToken) -> Self {
+        Self {
+            family_id: token.family_id,
+            service: token.service,
+            used_at: token.used_at,
+            expires_at: token.expires_at,
+            created_at: token.created_at,
+        }
+    }
+}
+
+impl RefreshToken {
+    pub async fn migrate() -> Result<Vec<String>, MongooseError> {
+        let created = Self::create_indexes(&[
+            IndexModel::builder()
+                .keys(doc! { "expires_at": 1 })
+                .options(
+                    IndexOptions::builder()
+                        .expire_after(Duration::from_secs(0))
+                        .build(),
+                )
+                .build(),
+            IndexModel::builder().keys(doc! { "user_id": 1 }).build(),
+            IndexModel::builder().keys(doc! { "family_id": 1 }).build(),
+        ])
+        .await?;
+        Ok(created.index_names)
+    }
+
+    pub fn hash(token: &str) -> String {
+        URL_SAFE_NO_PAD.encode(Sha256::digest(token.as_bytes()))
+    }
+
+    fn generate_token() -> String {
+        nanoid::nanoid!(64)
+    }
+
+    /// stores a new token and returns the raw value; pass a `family_id` when rotating
+    pub async fn issue(
+        user_id: &str,
+        service: &Service,
+        token_version: u32,
+        family_id: Option<String>,
+        ttl: Duration,
+    ) -> Result<String, AppError> {
+        let token = Self::generate_token();
+        let now = DateTime::now();
+        let ttl = i64::try_from(ttl.as_millis()).unwrap_or(i64::MAX);
+        Self {
+            id: Self::hash(&token),
+            user_id: user_id.to_string(),
+            family_id: family_id.unwrap_or_else(Self::generate_nanoid),
+            service: service.clone(),
+            token_version,
+            used_at: None,
+            expires_at: DateTime::from_millis(now.timestamp_millis() + ttl),
+            created_at: now,
+            updated_at: now,
+        }
+        .save()
+        .await
+        .map_err(AppError::internal_server_error)?;
+        Ok(token)
+    }
+
+    /// marks the token used and returns it. presenting an already used token
+    /// revokes its whole family, since either the client or an attacker holds a stolen copy
+    pub async fn consume(token: &str) -> Result<Self, AppError> {
+        let id = Self::hash(token);
+        let consumed = Self::update(
+            doc! { "_id": &id, "used_at": null },
+            doc! { "used_at": DateTime::now() },
+        )
+        .await;
+        let Ok(refresh_token) = consumed else {
+            if let Ok(reused) = Self::read_by_id(&id).await {
+                tracing::warn!(
+                    "[REFRESH TOKEN REUSE]: revoking family {} for user {}",
+                    reused.family_id,
+                    reused.user_id
+                );
+                Self::revoke_family(&reused.family_id).await?;
+            }
+            return Err(AppError::unauthorized("invalid refresh token"));
+        };
+        if refresh_token.expires_at < DateTime::now() {
+            // the ttl index sweeps lazily, so expiry is enforced here too
+            return Err(AppError::unauthorized("refresh token expired"));
+        }
+        Ok(refresh_token)
+    }
+
+    pub async fn revoke_family(family_id: &str) -> Result<u64, AppError> {
+        let deleted = Self::bulk_delete(doc! { "family_id": family_id })
+            .await
+            .map_err(AppError::internal_server_error)?;
+        Ok(deleted.deleted_count)
+    }
+
+    pub async fn list_for_user(user_id: &str) -> Result<Vec<Self>, AppError> {
+        Self::list(
+            doc! { "user_id": user_id },
+            mongoose::types::ListOptions {
+                limit: 0,
+                sort: doc! { "created_at": -1 },
+                ..Default::default()
+            },
+        )
+        .await
+        .map_err(AppError::internal_server_error)
+    }
+
+    pub async fn revoke_all(user_id: &str) -> Result<u64, AppError> {
+        let deleted = Self::bulk_delete(doc! { "user_id": user_id })
+            .await
+            .map_err(AppError::internal_server_error)?;
+        Ok(deleted.deleted_count)
+    }
+}
+
+impl Default for RefreshToken {
+    fn default() -> Self {
+        Self {
+            id: Self::generate_nanoid(),
+            user_id: String::default(),
+            family_id: Self::generate_nanoid(),
+            service: Service::default(),
+            token_version: 0,
+            used_at: None,
+            expires_at: DateTime::now(),
+            created_at: DateTime::now(),
+            updated_at: DateTime::now(),
+        }
+    }
+}
+
+impl Model for RefreshToken {}
@@ -1,28 +1,275 @@
-use axum::extract::Request;
-use mongoose::{doc, types::MongooseError, DateTime, IndexModel, IndexOptions, Model};
+use aws_sdk_dynamodb::Client as DynamoClient;
+use axum::http::HeaderMap;
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use bson::Document;
+use mongoose::{
+    doc,
+    types::{ListOptions, MongooseError},
+    AggregateOptions, DateTime, IndexModel, IndexOptions, Model,
+};
 use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, time::Duration};
 
 use crate::{
+    aws::s3::Bucket,
     env::Env,
-    errors::AppError,
-    jwt::{self, Claims, Service},
+    errors::{AppError, TokenError},
+    jwt::{self, Claims, TokenPair},
+    models::{
+        auth::{Auth as AuthRecord, PublicCredentials},
+        deleted_user::DeletedUser,
+        oauth_link_state::Provider,
+        refresh_token::{PublicSession, RefreshToken},
+        user_status_event::UserStatusEvent,
+    },
     oauth::{
-        self,
-        google::types::{GoogleAccessToken, GoogleUserInfo},
+        discord::types::DiscordUserInfo,
+        github::types::GithubUserInfo,
+        google::types::GoogleUserInfo,
+        oidc::types::OidcUserInfo,
+        types::{Identity, OAuthTokens},
+        OAuthProvider, Registry,
     },
+    rbac::{self, Permission, Role},
+    service::Service,
+    types::{UpdateProfile, UserListQuery},
 };
 
+pub const DEFAULT_PAGE_SIZE: i64 = 50;
+pub const MAX_PAGE_SIZE: i64 = 200;
+
+pub const DISPLAY_NAME_MAX_LENGTH: usize = 32;
+pub const BIO_MAX_LENGTH: usize = 280;
+pub const AVATAR_UPLOAD_TTL: Duration = Duration::from_secs(60 * 5);
+pub const AVATAR_MAX_BYTES: i64 = 2 * 1024 * 1024;
+// content type -> file extension
+const AVATAR_CONTENT_TYPES: [(&str, &str); 4] = [
+    ("image/png", "png"),
+    ("image/jpeg", "jpg"),
+    ("image/webp", "webp"),
+    ("image/gif", "gif"),
+];
+
 #[derive(Debug, Deserialize, Serialize, Clone, Default)]
-pub struct GoogleProviderInformation {
-    pub metadata: GoogleUserInfo,
-    pub tokens: GoogleAccessToken,
+pub struct ProviderInformation<M> {
+    pub metadata: M,
+    pub tokens: OAuthTokens,
+}
+
+pub type GoogleProviderInformation = ProviderInformation<GoogleUserInfo>;
+pub type GithubProviderInformation = ProviderInformation<GithubUserInfo>;
+pub type DiscordProviderInformation = ProviderInformation<DiscordUserInfo>;
+pub type OidcProviderInformation = ProviderInformation<OidcUserInfo>;
+
+#[derive(Debug, Deserialize, Serialize, Clone, Default)]
+pub struct PasswordProviderInformation {
+    pub auth_id: String, // dynamo `models::auth::Auth` id
+    pub username: String,
 }
 
 #[derive(Debug, Deserialize, Serialize, Clone, Default)]
 pub struct Auth {
     pub token_version: u32,
-    pub google: GoogleProviderInformation,
-    // other providers
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub google: Option<GoogleProviderInformation>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub github: Option<GithubProviderInformation>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub discord: Option<DiscordProviderInformation>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub password: Option<PasswordProviderInformation>,
+    // configured provider name -> login
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub oidc: BTreeMap<String, OidcProviderInformation>,
+}
+
+impl Auth {
+    pub fn tokens(&self, provider: &Provider) -> Option<&OAuthTokens> {
+        match provider {
+            Provider::GOOGLE => self.google.as_ref().map(|google| &google.tokens),
+            Provider::GITHUB => self.github.as_ref().map(|github| &github.tokens),
+            Provider::DISCORD => self.discord.as_ref().map(|discord| &discord.tokens),
+            Provider::OIDC(name) => self.oidc.get(name).map(|oidc| &oidc.tokens),
+        }
+    }
+
+    /// oauth providers the user has signed in with
+    pub fn providers(&self) -> Vec<Provider> {
+        Provider::BUILTIN
+            .into_iter()
+            .chain(
+                self.oidc
+                    .keys()
+                    .map(|name| Provider::OIDC(name.to_string())),
+            )
+            .filter(|provider| self.tokens(provider).is_some())
+            .collect()
+    }
+
+    /// every way the user can sign in: provider slugs, plus `password`
+    pub fn login_methods(&self) -> Vec<String> {
+        let mut methods = self
+            .providers()
+            .iter()
+            .map(|provider| provider.slug().to_string())
+            .collect::<Vec<_>>();
+        if self.password.is_some() {
+            methods.push("password".to_string());
+        }
+        methods
+    }
+
+    fn set_identity(&mut self, identity: Identity, tokens: OAuthTokens) {
+        match identity {
+            Identity::Google(metadata) => {
+                self.google = Some(ProviderInformation { metadata, tokens });
+            }
+            Identity::Github(metadata) => {
+                self.github = Some(ProviderInformation { metadata, tokens });
+            }
+            Identity::Discord(metadata) => {
+                self.discord = Some(ProviderInformation { metadata, tokens });
+            }
+            Identity::Oidc(name, metadata) => {
+                self.oidc
+                    .insert(name, ProviderInformation { metadata, tokens });
+            }
+        }
+    }
+}
+
+/// fields the user owns; seeded on the first login, never overwritten by later ones
+#[derive(Debug, Deserialize, Serialize, Clone, Default)]
+pub struct Profile {
+    pub display_name: Option<String>,
+    pub bio: Option<String>,
+    pub avatar_key: Option<String>, // s3 key under the user's prefix
+    pub locale: Option<String>,     // BCP 47 tag, e.g. `en-US`
+}
+
+impl Profile {
+    pub fn check_display_name(display_name: &str) -> Result<(), AppError> {
+        if display_name.trim().is_empty() {
+            return Err(AppError::bad_request("display_name cannot be empty"));
+        }
+        if display_name.chars().count() > DISPLAY_NAME_MAX_LENGTH {
+            return Err(AppError::bad_request(format!(
+                "display_name cannot be longer than {DISPLAY_NAME_MAX_LENGTH} characters"
+            )));
+        }
+        if display_name.chars().any(char::is_control) {
+            return Err(AppError::bad_request(
+                "display_name cannot contain control characters",
+            ));
+        }
+        Ok(())
+    }
+
+    pub fn check_bio(bio: &str) -> Result<(), AppError> {
+        if bio.chars().count() > BIO_MAX_LENGTH {
+            return Err(AppError::bad_request(format!(
+                "bio cannot be longer than {BIO_MAX_LENGTH} characters"
+            )));
+        }
+        // line breaks are fine, anything else invisible is not
+        if bio.chars().any(|c| c.is_control() && c != '\n') {
+            return Err(AppError::bad_request(
+                "bio cannot contain control characters",
+            ));
+        }
+        Ok(())
+    }
+
+    /// an uploaded avatar must match the type its key's extension names, within the size limit
+    pub fn check_avatar(
+        key: &str,
+        content_type: &str,
+        content_length: Option<i64>,
+    ) -> Result<(), AppError> {
+        let expected = key.rsplit_once('.').and_then(|(_, extension)| {
+            AVATAR_CONTENT_TYPES
+                .iter()
+                .find(|(_, allowed)| *allowed == extension)
+                .map(|(content_type, _)| *content_type)
+        });
+        if expected != Some(content_type) {
+            return Err(AppError::bad_request(format!(
+                "avatar has unexpected content type: {content_type}"
+            )));
+        }
+        if content_length.map_or(true, |length| length > AVATAR_MAX_BYTES) {
+            return Err(AppError::bad_request(format!(
+                "avatar cannot be larger than {AVATAR_MAX_BYTES} bytes"
+            )));
+        }
+        Ok(())
+    }
+
+    /// a language subtag followed by optional region or script subtags: `en`, `en-US`, `zh-Hant-TW`
+    pub fn check_locale(locale: &str) -> Result<(), AppError> {
+        let mut subtags = locale.split('-');
+        let language = subtags.next().unwrap_or_default();
+        let valid = (2..=3).contains(&language.len())
+            && language.chars().all(|c| c.is_ascii_alphabetic())
+            && subtags.all(|subtag| {
+                (1..=8).contains(&subtag.len()) && subtag.chars().all(|c| c.is_ascii_alphanumeric())
+            });
+        if !valid {
+            return Err(AppError::bad_request(format!("invalid locale: {locale}")));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, Default, PartialEq, Eq)]
+#[serde(tag = "state", rename_all = "lowercase")]
+pub enum AccountStatus {
+    #[default]
+    Active,
+    Suspended {
+        until: Option<DateTime>, // `None` until lifted by an admin
+        reason: String,
+    },
+    Banned {
+        reason: String,
+    },
+}
+
+impl AccountStatus {
+    /// a lapsed suspension counts as active without anyone having to lift it
+    pub fn is_active(&self) -> bool {
+        match self {
+            Self::Active => true,
+            Self::Suspended { until, .. } => until.is_some_and(|until| until <= DateTime::now()),
+            Self::Banned { .. } => false,
+        }
+    }
+
+    /// the forbidden error handed to a user who isn't active
+    pub fn check(&self) -> Result<(), AppError> {
+        if self.is_active() {
+            return Ok(());
+        }
+        match self {
+            Self::Suspended { until, reason } => Err(AppError::AccountRestricted {
+                code: "account_suspended",
+                message: until.map_or_else(
+                    || format!("account suspended: {reason}"),
+                    |until| {
+                        format!(
+                            "account suspended until {}: {reason}",
+                            until.try_to_rfc3339_string().unwrap_or_default()
+                        )
+                    },
+                ),
+            }),
+            Self::Banned { reason } => Err(AppError::AccountRestricted {
+                code: "account_banned",
+                message: format!("account banned: {reason}"),
+            }),
+            Self::Active => Ok(()),
+        }
+    }
 }
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
@@ -31,16 +278,118 @@ pub struct User {
     pub id: String,
     pub auth: Auth,
     pub service: Service,
+    #[serde(default)]
+    pub roles: Vec<Role>,
+    // granted on top of what `roles` give
+    #[serde(default)]
+    pub permissions: Vec<Permission>,
+    #[serde(default)]
+    pub status: AccountStatus,
+    #[serde(default)]
+    pub profile: Profile,
+    pub created_at: DateTime,
+    pub updated_at: DateTime,
+}
+
+#[derive(Debug, Serialize, Clone, Default)]
+pub struct PublicProviders {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub google: Option<GoogleUserInfo>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub github: Option<GithubUserInfo>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub discord: Option<DiscordUserInfo>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub password: Option<PasswordProviderInformation>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub oidc: BTreeMap<String, OidcUserInfo>,
+}
+
+/// what the api is allowed to return about a `User`: no provider tokens or token versions
+#[derive(Debug, Serialize, Clone)]
+pub struct PublicAccount {
+    pub id: String,
+    pub service: Service,
+    pub profile: Profile,
+    pub providers: PublicProviders,
+    pub roles: Vec<Role>,
     pub created_at: DateTime,
     pub updated_at: DateTime,
 }
 
+impl From<User> for PublicAccount {
+    fn from(user: User) -> Self {
+        Self {
+            id: user.id,
+            service: user.service,
+            profile: user.profile,
+            providers: PublicProviders {
+                google: user.auth.google.map(|google| google.metadata),
+                github: user.auth.github.map(|github| github.metadata),
+                discord: user.auth.discord.map(|discord| discord.metadata),
+                password: user.auth.password,
+                oidc: user
+                    .auth
+                    .oidc
+                    .into_iter()
+                    .map(|(name, oidc)| (name, oidc.metadata))
+                    .collect(),
+            },
+            roles: user.roles,
+            created_at: user.created_at,
+            updated_at: user.updated_at,
+        }
+    }
+}
+
+/// what admins see: the public view plus account state
+#[derive(Debug, Serialize, Clone)]
+pub struct AdminAccount {
+    #[serde(flatten)]
+    pub user: PublicAccount,
+    pub permissions: Vec<Permission>,
+    pub status: AccountStatus,
+    pub token_version: u32,
+}
+
+impl From<User> for AdminAccount {
+    fn from(user: User) -> Self {
+        Self {
+            permissions: user.effective_permissions(),
+            status: user.status.clone(),
+            token_version: user.auth.token_version,
+            user: PublicAccount::from(user),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct AccountPage {
+    pub users: Vec<AdminAccount>,
+    pub next_cursor: Option<String>,
+}
+
+/// everything held about a user, for `GET /oauth/me/export`
+#[derive(Debug, Serialize)]
+pub struct AccountExport {
+    pub exported_at: DateTime,
+    pub user: AdminAccount,
+    pub password_login: Option<PublicCredentials>,
+    pub sessions: Vec<PublicSession>,
+    pub status_history: Vec<UserStatusEvent>,
+    pub files: Vec<String>, // s3 keys under the user's prefix
+}
+
 impl Default for User {
     fn default() -> Self {
         Self {
             id: Self::generate_nanoid(),
             auth: Auth::default(),
-            service: Service::LOCALHOST,
+            service: Service::default(),
+            roles: vec![],
+            permissions: vec![],
+            status: AccountStatus::Active,
+            profile: Profile::default(),
             created_at: DateTime::now(),
             updated_at: DateTime::now(),
         }
@@ -52,111 +401,751 @@ impl User {
         bson::to_bson(&data).map_err(AppError::internal_server_error)
     }
 
-    pub async fn migrate() -> Result<Vec<String>, MongooseError> {
-        let created = Self::create_indexes(&[
-            IndexModel::builder()
-                .keys(doc! { "auth.google.metadata.id": 1 })
-                .options(IndexOptions::builder().unique(true).build())
-                .build(),
-            IndexModel::builder().keys(doc! { "service": 1 }).build(),
-        ])
+    /// a unique index on `field` scoped to the service, so one provider account can
+    /// sign in to every service but only once per service
+    fn unique_per_service(field: &str) -> IndexModel {
+        IndexModel::builder()
+            .keys(doc! { "service": 1, field: 1 })
+            .options(
+                IndexOptions::builder()
+                    .unique(true)
+                    .partial_filter_expression(doc! { field: { "$exists": true } })
+                    .build(),
+            )
+            .build()
+    }
+
+    /// drops the globally unique `{field}_1` indexes that predate per-service uniqueness
+    async fn drop_global_indexes(fields: &[String]) -> Result<Vec<String>, MongooseError> {
+        let collection = Self::collection().await;
+        let existing = collection
+            .list_index_names()
+            .await
+            .map_err(MongooseError::create_index)?;
+        let mut dropped = vec![];
+        for name in fields.iter().map(|field| format!("{field}_1")) {
+            if !existing.contains(&name) {
+                continue;
+            }
+            collection
+                .drop_index(&name, None)
+                .await
+                .map_err(MongooseError::create_index)?;
+            dropped.push(name);
+        }
+        Ok(dropped)
+    }
+
+    /// users written without a service, e.g. by hand, belong to the default one;
+    /// left without one they'd all share a `null` service in the unique indexes
+    async fn backfill_services() -> Result<u64, MongooseError> {
+        let updated = Self::bulk_update(
+            doc! { "service": null },
+            doc! { "service": Service::default().to_string() },
+        )
+        .await?;
+        Ok(updated.modified_count)
+    }
+
+    /// fails with the users sharing a value of `field` within one service, which
+    /// `create_indexes` would otherwise only report as a bare duplicate key error
+    async fn check_unique_per_service(field: &str) -> Result<(), MongooseError> {
+        #[derive(Deserialize)]
+        struct Duplicate {
+            #[serde(rename = "_id")]
+            key: Document,
+            users: Vec<String>,
+        }
+        let duplicates = Self::aggregate::<Duplicate>(
+            vec![
+                doc! { "$match": { field: { "$exists": true } } },
+                doc! {
+                    "$group": {
+                        "_id": { "service": "$service", "id": format!("${field}") },
+                        "users": { "$push": "$_id" },
+                    }
+                },
+                doc! { "$match": { "users.1": { "$exists": true } } },
+            ],
+            None::<AggregateOptions>,
+        )
         .await?;
+        if duplicates.is_empty() {
+            return Ok(());
+        }
+        let shared = duplicates
+            .iter()
+            .map(|duplicate| format!("{} ({})", duplicate.key, duplicate.users.join(", ")))
+            .collect::<Vec<_>>()
+            .join("; ");
+        Err(MongooseError::CreateIndex(format!(
+            "{field} is shared within a service, merge or remove these users first: {shared}"
+        )))
+    }
+
+    /// swaps the global unique indexes on `fields` for per-service ones, alongside `indexes`
+    async fn scope_unique_indexes(
+        fields: &[String],
+        mut indexes: Vec<IndexModel>,
+    ) -> Result<Vec<String>, MongooseError> {
+        for field in fields {
+            Self::check_unique_per_service(field).await?;
+        }
+        indexes.extend(fields.iter().map(|field| Self::unique_per_service(field)));
+        // new indexes first, so uniqueness is never unenforced in between
+        let created = Self::create_indexes(&indexes).await?;
+        for name in Self::drop_global_indexes(fields).await? {
+            tracing::info!("dropped global index {name}");
+        }
         Ok(created.index_names)
     }
 
-    pub async fn create_or_update_google(
+    pub async fn migrate() -> Result<Vec<String>, MongooseError> {
+        let backfilled = Self::backfill_services().await?;
+        if backfilled > 0 {
+            tracing::info!("moved {backfilled} users without a service to the default service");
+        }
+        let fields = [
+            "auth.google.metadata.id",
+            "auth.github.metadata.id",
+            "auth.discord.metadata.id",
+            "auth.password.auth_id",
+        ];
+        Self::scope_unique_indexes(
+            &fields.map(String::from),
+            vec![
+                IndexModel::builder().keys(doc! { "service": 1 }).build(),
+                // admin listing pages newest first
+                IndexModel::builder()
+                    .keys(doc! { "created_at": -1, "_id": -1 })
+                    .build(),
+            ],
+        )
+        .await
+    }
+
+    /// oidc providers come from config, so their unique indexes are made per name
+    pub async fn migrate_oidc(names: &[String]) -> Result<Vec<String>, MongooseError> {
+        if names.is_empty() {
+            return Ok(vec![]);
+        }
+        let fields = names
+            .iter()
+            .map(|name| format!("auth.oidc.{name}.metadata.sub"))
+            .collect::<Vec<_>>();
+        Self::scope_unique_indexes(&fields, vec![]).await
+    }
+
+    pub async fn create_or_update(
         service: Service,
-        google_user_info: GoogleUserInfo,
-        token_data: GoogleAccessToken,
+        identity: Identity,
+        tokens: OAuthTokens,
     ) -> Result<Self, AppError> {
-        if let Ok(user) = Self::read(doc! {
+        let key = identity.provider().auth_key();
+        if let Some(user) = Self::find_one(doc! {
             "service": service.to_string(),
-            "auth.google.metadata.id": google_user_info.id.to_string()
+            (format!("auth.{key}.metadata.{}", identity.id_field())): identity.id(),
         })
-        .await
+        .await?
         {
             let updates = doc! {
-                "auth.google": {
-                    "metadata": Self::to_bson(google_user_info)?,
-                    "tokens": Self::to_bson(token_data)?,
+                (format!("auth.{key}")): {
+                    "metadata": identity.metadata()?,
+                    "tokens": Self::to_bson(&tokens)?,
                 }
             };
             return Self::update(doc! { "_id": user.id }, updates)
                 .await
                 .map_err(AppError::bad_request);
         };
-        // else build new user
-        let user = Self {
+        // else build new user, seeding the profile from the provider once
+        let display_name = identity
+            .display_name()
+            .map(|name| name.chars().take(DISPLAY_NAME_MAX_LENGTH).collect());
+        let mut user = Self {
             service,
-            auth: Auth {
-                google: GoogleProviderInformation {
-                    tokens: token_data,
-                    metadata: google_user_info,
-                },
+            profile: Profile {
+                display_name,
                 ..Default::default()
             },
             ..Default::default()
         };
+        user.auth.set_identity(identity, tokens);
         let user = user.save().await.map_err(AppError::bad_request)?;
         Ok(user)
     }
 
-    pub async fn refresh_google_tokens(&self, env: &Env) -> Result<Self, AppError> {
-        let refresh_token = &self.auth.google.tokens.refresh_token;
-        let tokens = oauth::google::refresh_tokens(
-            &env.google_client_id,
-            &env.google_client_secret,
-            refresh_token,
-        )
-        .await?;
+    /// attaches a provider identity to an existing user instead of signing in as whoever owns it
+    pub async fn link_identity(
+        user_id: &str,
+        identity: Identity,
+        tokens: OAuthTokens,
+    ) -> Result<Self, AppError> {
+        let user = Self::read_by_id(user_id)
+            .await
+            .map_err(AppError::not_found)?;
+        let provider = identity.provider();
+        let key = provider.auth_key();
+        if let Some(owner) = Self::find_one(doc! {
+            "service": user.service.to_string(),
+            (format!("auth.{key}.metadata.{}", identity.id_field())): identity.id(),
+        })
+        .await?
+        {
+            if owner.id != user.id {
+                return Err(AppError::bad_request(format!(
+                    "this {provider} account is already linked to another user"
+                )));
+            }
+        } else if user.auth.tokens(&provider).is_some() {
+            // one identity per provider; swapping accounts means unlinking first
+            return Err(AppError::bad_request(format!(
+                "unlink your current {provider} login first"
+            )));
+        }
         let updates = doc! {
-            "auth.google.tokens": {
-                "access_token": tokens.access_token,
-                "expires_in": tokens.expires_in,
-                "token_type": tokens.token_type,
-                "scope": tokens.scope,
-                "refresh_token": refresh_token,
+            (format!("auth.{key}")): {
+                "metadata": identity.metadata()?,
+                "tokens": Self::to_bson(&tokens)?,
+            }
+        };
+        Self::update(doc! { "_id": &user.id }, updates)
+            .await
+            .map_err(AppError::bad_request)
+    }
+
+    /// removes a login method; the last one can't be removed, or the account would be unreachable
+    pub async fn unlink(
+        dynamo: &DynamoClient,
+        user: &Self,
+        method: &str,
+    ) -> Result<Self, AppError> {
+        let provider = match method.to_lowercase().as_str() {
+            "password" => None,
+            other => Some(other.parse::<Provider>()?),
+        };
+        let linked = provider.as_ref().map_or_else(
+            || user.auth.password.is_some(),
+            |provider| user.auth.tokens(provider).is_some(),
+        );
+        if !linked {
+            return Err(AppError::not_found(format!("{method} is not linked")));
+        }
+        let key = provider
+            .as_ref()
+            .map_or_else(|| "password".to_string(), Provider::auth_key);
+        // the filter, not `user`, decides whether another method is left, so concurrent
+        // unlinks can't each remove one of the last two
+        let updated = match Self::update(
+            Self::unlink_filter(&user.id, &key),
+            doc! { "$unset": { (format!("auth.{key}")): "" } },
+        )
+        .await
+        {
+            Ok(updated) => updated,
+            Err(MongooseError::NotFound(_)) => {
+                return Err(AppError::bad_request(
+                    "cannot remove your last login method",
+                ))
             }
+            Err(err) => return Err(AppError::internal_server_error(err)),
+        };
+        // the dynamo credentials go with it, once the user no longer points at them
+        if provider.is_none() {
+            if let Some(password) = &user.auth.password {
+                AuthRecord::delete(dynamo, &password.auth_id).await?;
+            }
+        }
+        Ok(updated)
+    }
+
+    /// matches user `id` only while `auth.{key}` is linked alongside some other login method
+    fn unlink_filter(id: &str, key: &str) -> Document {
+        let mut others = ["google", "github", "discord", "password"]
+            .into_iter()
+            .filter(|other| *other != key)
+            .map(|other| doc! { (format!("auth.{other}")): { "$type": "object" } })
+            .collect::<Vec<_>>();
+        // oidc logins share one map, so count its entries
+        let oidc_logins = i32::from(key.starts_with("oidc."));
+        others.push(doc! {
+            "$expr": {
+                "$gt": [
+                    { "$size": { "$objectToArray": { "$ifNull": ["$auth.oidc", {}] } } },
+                    oidc_logins,
+                ]
+            }
+        });
+        doc! {
+            "_id": id,
+            (format!("auth.{key}")): { "$type": "object" },
+            "$or": others,
+        }
+    }
+
+    /// `None` only when no document matches; mongoose's `read` reports driver errors as `NotFound` too
+    pub async fn find_one(filter: Document) -> Result<Option<Self>, AppError> {
+        Self::collection()
+            .await
+            .find_one(filter, None)
+            .await
+            .map_err(AppError::internal_server_error)
+    }
+
+    pub async fn find_by_id(id: &str) -> Result<Option<Self>, AppError> {
+        Self::find_one(doc! { "_id": id }).await
+    }
+
+    /// admins only ever see users of their own service
+    pub async fn read_in_service(id: &str, service: &Service) -> Result<Self, AppError> {
+        Self::find_one(doc! { "_id": id, "service": service.to_string() })
+            .await?
+            .ok_or_else(|| AppError::not_found("user not found"))
+    }
+
+    pub async fn create_password(service: Service, auth: &AuthRecord) -> Result<Self, AppError> {
+        let user = Self {
+            id: auth.user_id.clone().unwrap_or_else(Self::generate_nanoid),
+            service,
+            auth: Auth {
+                password: Some(PasswordProviderInformation {
+                    auth_id: auth.id.to_string(),
+                    username: auth.username.to_string(),
+                }),
+                ..Default::default()
+            },
+            ..Default::default()
         };
+        user.save().await.map_err(AppError::bad_request)
+    }
+
+    pub async fn refresh_provider_tokens(
+        &self,
+        provider: &dyn OAuthProvider,
+    ) -> Result<Self, AppError> {
+        let key = provider.provider().auth_key();
+        let current = self
+            .auth
+            .tokens(&provider.provider())
+            .ok_or_else(|| AppError::bad_request(format!("user has no {key} login")))?;
+        let mut tokens = provider
+            .refresh_tokens(current.refresh_token.expose())
+            .await?;
+        // providers only return a refresh token when they rotate it
+        if tokens.refresh_token.expose().is_empty() {
+            tokens.refresh_token = current.refresh_token.clone();
+        }
+        let updates = doc! { (format!("auth.{key}.tokens")): Self::to_bson(&tokens)? };
         Self::update(doc! { "_id": &self.id }, updates)
             .await
             .map_err(AppError::internal_server_error)
     }
 
-    pub fn sign_token(&self, secret: &str) -> Result<String, AppError> {
-        jwt::sign(&self.id, self.auth.token_version, self.service, secret)
+    /// signs an access token with the lifetime configured for the user's service
+    pub fn sign_token(&self, env: &Env) -> Result<(String, chrono::Duration), AppError> {
+        let config = env.services.get(&self.service)?;
+        let ttl = chrono::Duration::from_std(config.access_token_ttl())
+            .map_err(AppError::internal_server_error)?;
+        let claims = Claims {
+            roles: self.roles.clone(),
+            permissions: self.effective_permissions(),
+            ..Claims::new(
+                &env.public_url,
+                &self.id,
+                &self.service,
+                self.auth.token_version,
+                ttl,
+            )
+        };
+        let token = jwt::sign(&claims, &env.jwt_keys)?;
+        Ok((token, ttl))
     }
 
-    pub fn verify_token(token: &str, secret: &str) -> Result<Claims, AppError> {
-        jwt::verify(token, secret)
+    /// starts a new refresh token family alongside a fresh access token
+    pub async fn issue_tokens(&self, env: &Env) -> Result<TokenPair, AppError> {
+        self.ensure_active()?;
+        let config = env.services.get(&self.service)?;
+        let refresh_token = RefreshToken::issue(
+            &self.id,
+            &self.service,
+            self.auth.token_version,
+            None,
+            config.refresh_token_ttl(),
+        )
+        .await?;
+        let (access_token, expires_in) = self.sign_token(env)?;
+        Ok(TokenPair::bearer(access_token, expires_in, refresh_token))
     }
 
-    pub async fn authenticate(req: Request, secret: &str) -> Result<Self, AppError> {
-        let headers = req.headers();
-        let auth = headers
-            .get("authorization")
-            .ok_or_else(|| AppError::unauthorized("missing auth header"))?;
-        let parts = auth
-            .to_str()
-            .map_err(AppError::internal_server_error)?
-            .split_ascii_whitespace()
-            .collect::<Vec<_>>();
-        let token = *parts
-            .get(1)
-            .ok_or_else(|| AppError::unauthorized("missing auth token"))?;
+    /// exchanges a refresh token for a new pair within the same family
+    pub async fn refresh_tokens(refresh_token: &str, env: &Env) -> Result<TokenPair, AppError> {
+        let consumed = RefreshToken::consume(refresh_token).await?;
+        let user = Self::read_by_id(&consumed.user_id)
+            .await
+            .map_err(AppError::unauthorized)?;
+        user.ensure_active()?;
+        if consumed.token_version != user.auth.token_version || consumed.service != user.service {
+            RefreshToken::revoke_family(&consumed.family_id).await?;
+            return Err(AppError::unauthorized("invalid refresh token"));
+        }
+        let config = env.services.get(&user.service)?;
+        let rotated = RefreshToken::issue(
+            &user.id,
+            &user.service,
+            user.auth.token_version,
+            Some(consumed.family_id),
+            config.refresh_token_ttl(),
+        )
+        .await?;
+        let (access_token, expires_in) = user.sign_token(env)?;
+        Ok(TokenPair::bearer(access_token, expires_in, rotated))
+    }
+
+    /// invalidates every access and refresh token issued to the user so far
+    pub async fn revoke_sessions(id: &str) -> Result<Self, AppError> {
+        let user = Self::update(
+            doc! { "_id": id },
+            doc! { "$inc": { "auth.token_version": 1 } },
+        )
+        .await
+        .map_err(AppError::not_found)?;
+        RefreshToken::revoke_all(&user.id).await?;
+        Ok(user)
+    }
+
+    /// a page of `service`'s users, newest first; `query.service` may only name that same
+    /// service, and `next_cursor` is opaque and resumes after the last user returned
+    pub async fn list_page(
+        service: &Service,
+        query: &UserListQuery,
+    ) -> Result<AccountPage, AppError> {
+        let limit = query
+            .limit
+            .unwrap_or(DEFAULT_PAGE_SIZE)
+            .clamp(1, MAX_PAGE_SIZE);
+        if query
+            .service
+            .as_ref()
+            .is_some_and(|requested| &Service::new(requested) != service)
+        {
+            return Err(AppError::forbidden("cannot list users of another service"));
+        }
+        let mut filters = vec![doc! { "service": service.to_string() }];
+        if let Some(provider) = &query.provider {
+            let key = match provider.to_lowercase().as_str() {
+                "password" => "password".to_string(),
+                other => other.parse::<Provider>()?.auth_key(),
+            };
+            filters.push(doc! { (format!("auth.{key}")): { "$exists": true } });
+        }
+        if let Some(status) = &query.status {
+            let now = DateTime::now();
+            let status = status.to_lowercase();
+            // a lapsed suspension is active, matching `AccountStatus::is_active`
+            match status.as_str() {
+                "active" => filters.push(doc! {
+                    "$or": [
+                        { "status.state": "active" },
+                        // users from before statuses existed have none stored
+                        { "status": { "$exists": false } },
+                        { "status.state": "suspended", "status.until": { "$lte": now } },
+                    ]
+                }),
+                "suspended" => filters.push(doc! {
+                    "status.state": "suspended",
+                    "$or": [{ "status.until": null }, { "status.until": { "$gt": now } }],
+                }),
+                "banned" => filters.push(doc! { "status.state": "banned" }),
+                other => return Err(AppError::bad_request(format!("unknown status: {other}"))),
+            }
+        }
+        if let Some(after) = query.created_after {
+            let after = DateTime::from_millis(after.timestamp_millis());
+            filters.push(doc! { "created_at": { "$gte": after } });
+        }
+        if let Some(before) = query.created_before {
+            let before = DateTime::from_millis(before.timestamp_millis());
+            filters.push(doc! { "created_at": { "$lt": before } });
+        }
+        if let Some(cursor) = &query.cursor {
+            let (created_at, id) = Self::decode_cursor(cursor)?;
+            filters.push(doc! {
+                "$or": [
+                    { "created_at": { "$lt": created_at } },
+                    { "created_at": created_at, "_id": { "$lt": id } },
+                ]
+            });
+        }
+        // one extra tells us whether there's another page
+        let mut users = Self::list(
+            doc! { "$and": filters },
+            ListOptions {
+                limit: limit + 1,
+                sort: doc! { "created_at": -1, "_id": -1 },
+                ..Default::default()
+            },
+        )
+        .await
+        .map_err(AppError::internal_server_error)?;
+        let has_more = users.len() > usize::try_from(limit).unwrap_or(usize::MAX);
+        users.truncate(usize::try_from(limit).unwrap_or(usize::MAX));
+        let next_cursor = users
+            .last()
+            .filter(|_| has_more)
+            .map(|last| Self::encode_cursor(last.created_at, &last.id));
+        Ok(AccountPage {
+            users: users.into_iter().map(AdminAccount::from).collect(),
+            next_cursor,
+        })
+    }
+
+    fn encode_cursor(created_at: DateTime, id: &str) -> String {
+        URL_SAFE_NO_PAD.encode(format!("{}:{id}", created_at.timestamp_millis()))
+    }
+
+    fn decode_cursor(cursor: &str) -> Result<(DateTime, String), AppError> {
+        let invalid = || AppError::bad_request("invalid cursor");
+        let decoded = URL_SAFE_NO_PAD.decode(cursor).map_err(|_| invalid())?;
+        let decoded = String::from_utf8(decoded).map_err(|_| invalid())?;
+        let (millis, id) = decoded.split_once(':').ok_or_else(invalid)?;
+        let millis = millis.parse::<i64>().map_err(|_| invalid())?;
+        Ok((DateTime::from_millis(millis), id.to_string()))
+    }
+
+    /// suspended and banned users keep their data but can't sign in or use existing tokens.
+    /// every change is recorded as a `UserStatusEvent`
+    pub async fn set_status(
+        id: &str,
+        status: AccountStatus,
+        actor_id: Option<&str>,
+    ) -> Result<Self, AppError> {
+        let previous = Self::read_by_id(id).await.map_err(AppError::not_found)?;
+        let updated = Self::update(
+            doc! { "_id": id },
+            doc! { "status": Self::to_bson(&status)? },
+        )
+        .await
+        .map_err(AppError::not_found)?;
+        let user = if status.is_active() {
+            updated
+        } else {
+            Self::revoke_sessions(id).await?
+        };
+        UserStatusEvent::record(id, actor_id, previous.status, status).await?;
+        Ok(user)
+    }
+
+    pub fn ensure_active(&self) -> Result<(), AppError> {
+        self.status.check()
+    }
+
+    /// where the user's own files live in the bucket
+    pub fn storage_prefix(&self) -> String {
+        format!("users/{}/", self.id)
+    }
+
+    /// revokes provider grants, then removes the user, their sessions, any password login
+    /// and their files, leaving a `DeletedUser` tombstone behind
+    pub async fn delete_account(
+        dynamo: &DynamoClient,
+        bucket: &Bucket,
+        oauth: &Registry,
+        id: &str,
+        actor_id: Option<&str>,
+    ) -> Result<Self, AppError> {
+        let user = Self::read_by_id(id).await.map_err(AppError::not_found)?;
+        for provider in user.auth.providers() {
+            let Some(tokens) = user.auth.tokens(&provider) else {
+                continue;
+            };
+            // best effort: a grant the user already revoked shouldn't block deletion
+            let revoked = match oauth.get(&provider) {
+                Ok(client) => client.revoke(tokens).await,
+                Err(err) => Err(err),
+            };
+            if let Err(err) = revoked {
+                tracing::warn!("revoking {provider} grant for {}: {err:?}", user.id);
+            }
+        }
+        // files go before credentials, so a failed deletion can still be retried by signing in;
+        // every step after is safe to repeat
+        let deleted_objects = bucket.delete_prefix(user.storage_prefix()).await?;
+        DeletedUser::record(&user, actor_id, deleted_objects).await?;
+        RefreshToken::revoke_all(&user.id).await?;
+        if let Some(password) = &user.auth.password {
+            AuthRecord::delete(dynamo, &password.auth_id).await?;
+        }
+        Self::delete(doc! { "_id": &user.id })
+            .await
+            .map_err(AppError::internal_server_error)?;
+        Ok(user)
+    }
+
+    /// applies a `PATCH /oauth/me`; an avatar must have been uploaded under the user's prefix first
+    pub async fn update_profile(
+        &self,
+        bucket: &Bucket,
+        profile: UpdateProfile,
+    ) -> Result<Self, AppError> {
+        let mut set = Document::new();
+        let mut unset = Document::new();
+        let fields = [
+            ("display_name", profile.display_name),
+            ("bio", profile.bio),
+            ("avatar_key", profile.avatar_key),
+            ("locale", profile.locale),
+        ];
+        for (field, value) in fields {
+            let Some(value) = value.map(|value| value.trim().to_string()) else {
+                continue;
+            };
+            if value.is_empty() {
+                unset.insert(format!("profile.{field}"), "");
+                continue;
+            }
+            match field {
+                "display_name" => Profile::check_display_name(&value)?,
+                "bio" => Profile::check_bio(&value)?,
+                "locale" => Profile::check_locale(&value)?,
+                _ => self.check_avatar_key(bucket, &value).await?,
+            }
+            set.insert(format!("profile.{field}"), value);
+        }
+        let mut updates = set;
+        if !unset.is_empty() {
+            updates.insert("$unset", unset);
+        }
+        let user = Self::update(doc! { "_id": &self.id }, updates)
+            .await
+            .map_err(AppError::internal_server_error)?;
+        // the replaced avatar is no longer referenced anywhere
+        if let Some(previous) = &self.profile.avatar_key {
+            if user.profile.avatar_key.as_ref() != Some(previous) {
+                if let Err(err) = bucket.delete_object(previous).await {
+                    tracing::warn!("deleting old avatar {previous}: {err:?}");
+                }
+            }
+        }
+        Ok(user)
+    }
+
+    async fn check_avatar_key(&self, bucket: &Bucket, key: &str) -> Result<(), AppError> {
+        if !key.starts_with(&format!("{}avatars/", self.storage_prefix())) {
+            return Err(AppError::bad_request(
+                "avatar_key does not belong to this user",
+            ));
+        }
+        let head = bucket
+            .head_object(key)
+            .await?
+            .ok_or_else(|| AppError::bad_request("avatar has not been uploaded"))?;
+        // the presigned url pins the content type but not the size, so both are checked here
+        let checked = Profile::check_avatar(
+            key,
+            head.content_type().unwrap_or_default(),
+            head.content_length(),
+        );
+        if checked.is_err() {
+            // nothing will ever point at it
+            bucket.delete_object(key).await?;
+        }
+        checked
+    }
+
+    /// a fresh key for an avatar upload, and a presigned `PUT` url for it that must be
+    /// sent with the returned content type
+    pub async fn avatar_upload_url(
+        &self,
+        bucket: &Bucket,
+        content_type: &str,
+    ) -> Result<(String, String, &'static str), AppError> {
+        let (content_type, extension) = AVATAR_CONTENT_TYPES
+            .iter()
+            .find(|(allowed, _)| allowed.eq_ignore_ascii_case(content_type.trim()))
+            .ok_or_else(|| {
+                AppError::bad_request(format!("unsupported avatar content type: {content_type}"))
+            })?;
+        let key = format!(
+            "{}avatars/{}.{extension}",
+            self.storage_prefix(),
+            nanoid::nanoid!()
+        );
+        let url = bucket
+            .put_presigned_url_with_content_type(&key, content_type, AVATAR_UPLOAD_TTL)
+            .await?;
+        Ok((key, url, content_type))
+    }
+
+    pub async fn export(
+        &self,
+        dynamo: &DynamoClient,
+        bucket: &Bucket,
+    ) -> Result<AccountExport, AppError> {
+        let password_login = match &self.auth.password {
+            Some(password) => Some(
+                AuthRecord::get_by_id(dynamo, &password.auth_id)
+                    .await?
+                    .into(),
+            ),
+            None => None,
+        };
+        let sessions = RefreshToken::list_for_user(&self.id).await?;
+        Ok(AccountExport {
+            exported_at: DateTime::now(),
+            user: AdminAccount::from(self.clone()),
+            password_login,
+            sessions: sessions.into_iter().map(PublicSession::from).collect(),
+            status_history: UserStatusEvent::history(&self.id).await?,
+            files: bucket.list_objects(self.storage_prefix()).await?,
+        })
+    }
+
+    pub fn effective_permissions(&self) -> Vec<Permission> {
+        rbac::effective_permissions(&self.roles, &self.permissions)
+    }
+
+    pub fn has_permissions(&self, required: &[Permission]) -> bool {
+        let granted = self.effective_permissions();
+        required
+            .iter()
+            .all(|permission| granted.contains(permission))
+    }
+
+    /// replaces the user's roles; sessions are revoked so new tokens carry them
+    pub async fn set_roles(id: &str, roles: &[Role]) -> Result<Self, AppError> {
+        let roles = roles
+            .iter()
+            .copied()
+            .collect::<std::collections::BTreeSet<_>>();
+        Self::update(doc! { "_id": id }, doc! { "roles": Self::to_bson(roles)? })
+            .await
+            .map_err(AppError::not_found)?;
+        Self::revoke_sessions(id).await
+    }
+
+    pub fn verify_token(token: &str, env: &Env, audience: &Service) -> Result<Claims, AppError> {
+        jwt::verify(token, &env.jwt_keys, &env.public_url, audience)
+    }
+
+    /// resolves the bearer token's user for the service named in the request headers
+    pub async fn authenticate(headers: &HeaderMap, env: &Env) -> Result<Self, AppError> {
+        let service = env.services.from_headers(headers)?;
+        let token = jwt::bearer_token(headers)?;
         let Claims {
-            user_id,
+            sub,
             token_version,
-            issuer,
+            aud,
             ..
-        } = Self::verify_token(token, secret)?;
-        let user = Self::read_by_id(user_id)
-            .await
-            .map_err(AppError::internal_server_error)?;
+        } = Self::verify_token(token, env, &service.id)?;
+        // a deleted user's tokens outlive it until they expire
+        let user = Self::find_by_id(&sub)
+            .await?
+            .ok_or(AppError::InvalidToken(TokenError::UnknownUser))?;
         if token_version != user.auth.token_version {
-            return Err(AppError::unauthorized("invalid token version"));
+            return Err(AppError::InvalidToken(TokenError::Revoked));
         }
-        if issuer != user.service {
+        user.ensure_active()?;
+        if aud != user.service {
             return Err(AppError::forbidden(
                 "you do not have permission to access this service",
             ));
@@ -0,0 +1,74 @@
+use mongoose::{doc, types::MongooseError, DateTime, IndexModel, Model};
+use serde::{Deserialize, Serialize};
+
+use crate::{errors::AppError, models::user::AccountStatus};
+
+/// audit trail of every status change, kept even after the user is reinstated
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct UserStatusEvent {
+    #[serde(rename = "_id")]
+    pub id: String,
+    pub user_id: String,
+    pub actor_id: Option<String>, // admin who made the change; `None` from scripts
+    pub from: AccountStatus,
+    pub to: AccountStatus,
+    pub created_at: DateTime,
+    pub updated_at: DateTime,
+}
+
+impl UserStatusEvent {
+    pub async fn migrate() -> Result<Vec<String>, MongooseError> {
+        let created = Self::create_indexes(&[IndexModel::builder()
+            .keys(doc! { "user_id": 1, "created_at": -1 })
+            .build()])
+        .await?;
+        Ok(created.index_names)
+    }
+
+    pub async fn record(
+        user_id: &str,
+        actor_id: Option<&str>,
+        from: AccountStatus,
+        to: AccountStatus,
+    ) -> Result<Self, AppError> {
+        Self {
+            user_id: user_id.to_string(),
+            actor_id: actor_id.map(ToString::to_string),
+            from,
+            to,
+            ..Default::default()
+        }
+        .save()
+        .await
+        .map_err(AppError::internal_server_error)
+    }
+
+    pub async fn history(user_id: &str) -> Result<Vec<Self>, AppError> {
+        Self::list(
+            doc! { "user_id": user_id },
+            mongoose::types::ListOptions {
+                limit: 0,
+                sort: doc! { "created_at": -1 },
+                ..Default::default()
+            },
+        )
+        .await
+        .map_err(AppError::internal_server_error)
+    }
+}
+
+impl Default for UserStatusEvent {
+    fn default() -> Self {
+        Self {
+            id: Self::generate_nanoid(),
+            user_id: String::default(),
+            actor_id: None,
+            from: AccountStatus::default(),
+            to: AccountStatus::default(),
+            created_at: DateTime::now(),
+            updated_at: DateTime::now(),
+        }
+    }
+}
+
+impl Model for UserStatusEvent {}
@@ -0,0 +1,116 @@
+use async_trait::async_trait;
+use reqwest::{Client, Url};
+use types::DiscordUserInfo;
+
+use crate::{
+    errors::AppError,
+    models::oauth_link_state::{LinkState, Provider},
+};
+
+use super::{
+    types::{Identity, OAuthTokens},
+    OAuthProvider,
+};
+
+pub mod types {
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Debug, Deserialize, Serialize, Clone, Default)]
+    pub struct DiscordUserInfo {
+        pub id: String, // snowflake
+        pub username: String,
+        pub global_name: Option<String>,
+        pub avatar: Option<String>, // hash, see https://discord.com/developers/docs/reference#image-formatting
+        pub email: Option<String>,
+        pub verified: Option<bool>,
+    }
+}
+
+const DISCORD_OAUTH_ENDPOINT: &str = "https://discord.com/oauth2/authorize";
+const DISCORD_TOKEN_ENDPOINT: &str = "https://discord.com/api/oauth2/token";
+const DISCORD_USER_INFO_ENDPOINT: &str = "https://discord.com/api/users/@me";
+const DISCORD_SCOPES: [&str; 2] = ["identify", "email"];
+
+pub struct Discord {
+    client_id: String,
+    client_secret: String,
+}
+
+impl Discord {
+    pub fn new(client_id: &str, client_secret: &str) -> Self {
+        Self {
+            client_id: client_id.to_string(),
+            client_secret: client_secret.to_string(),
+        }
+    }
+
+    async fn request_tokens(&self, query: &[(&str, &str)]) -> Result<OAuthTokens, AppError> {
+        let response = Client::new()
+            .post(DISCORD_TOKEN_ENDPOINT)
+            .basic_auth(&self.client_id, Some(&self.client_secret))
+            .form(query)
+            .send()
+            .await
+            .map_err(AppError::unauthorized)?;
+        response.json().await.map_err(AppError::unauthorized)
+    }
+}
+
+#[async_trait]
+impl OAuthProvider for Discord {
+    fn provider(&self) -> Provider {
+        Provider::DISCORD
+    }
+
+    async fn authorize_url(&self, state: &LinkState) -> Result<String, AppError> {
+        let scope = DISCORD_SCOPES.join(" ");
+        let code_challenge = state.code_challenge();
+        let query = [
+            ("client_id", self.client_id.as_str()),
+            ("redirect_uri", state.redirect.as_str()),
+            ("response_type", "code"),
+            ("scope", scope.as_str()),
+            ("state", state.id.as_str()),
+            ("prompt", "consent"),
+            ("code_challenge", code_challenge.as_str()),
+            ("code_challenge_method", "S256"),
+        ];
+        let url =
+            Url::parse_with_params(DISCORD_OAUTH_ENDPOINT, query).map_err(AppError::bad_request)?;
+        Ok(url.to_string())
+    }
+
+    async fn exchange_code(&self, code: &str, state: &LinkState) -> Result<OAuthTokens, AppError> {
+        self.request_tokens(&[
+            ("grant_type", "authorization_code"),
+            ("code", code),
+            ("redirect_uri", state.redirect.as_str()),
+            ("code_verifier", state.code_verifier.expose()),
+        ])
+        .await
+    }
+
+    async fn fetch_user_info(
+        &self,
+        tokens: &OAuthTokens,
+        _state: &LinkState,
+    ) -> Result<Identity, AppError> {
+        let response = Client::new()
+            .get(DISCORD_USER_INFO_ENDPOINT)
+            .bearer_auth(tokens.access_token.expose())
+            .send()
+            .await
+            .map_err(AppError::not_found)?;
+        let info: DiscordUserInfo = response.json().await.map_err(AppError::unauthorized)?;
+        Ok(Identity::Discord(info))
+    }
+
+    async fn refresh_tokens(&self, refresh_token: &str) -> Result<OAuthTokens, AppError> {
+        // discord rotates the refresh token on every use
+        self.request_tokens(&[
+            ("grant_type", "refresh_token"),
+            ("refresh_token", refresh_token),
+        ])
+        .await
+    }
+}
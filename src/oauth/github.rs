@@ -0,0 +1,161 @@
+use async_trait::async_trait;
+use reqwest::{header, Client, RequestBuilder, Url};
+use types::{GithubEmail, GithubUserInfo};
+
+use crate::{
+    errors::AppError,
+    models::oauth_link_state::{LinkState, Provider},
+};
+
+use super::{
+    types::{Identity, OAuthTokens},
+    OAuthProvider,
+};
+
+pub mod types {
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Debug, Deserialize, Serialize, Clone, Default)]
+    pub struct GithubUserInfo {
+        pub id: i64,
+        pub login: String,
+        pub name: Option<String>,
+        pub email: Option<String>,
+        pub avatar_url: String,
+    }
+
+    #[derive(Debug, Deserialize)]
+    pub struct GithubEmail {
+        pub email: String,
+        pub primary: bool,
+        pub verified: bool,
+    }
+}
+
+pub const GITHUB_OAUTH_URL: &str = "https://github.com";
+pub const GITHUB_API_URL: &str = "https://api.github.com";
+const GITHUB_SCOPES: [&str; 2] = ["read:user", "user:email"];
+const USER_AGENT: &str = "pixel-collector-api";
+
+pub struct Github {
+    client_id: String,
+    client_secret: String,
+    oauth_url: String, // overridable so tests can point at a mock server
+    api_url: String,
+}
+
+impl Github {
+    pub fn new(client_id: &str, client_secret: &str, oauth_url: &str, api_url: &str) -> Self {
+        Self {
+            client_id: client_id.to_string(),
+            client_secret: client_secret.to_string(),
+            oauth_url: oauth_url.trim_end_matches('/').to_string(),
+            api_url: api_url.trim_end_matches('/').to_string(),
+        }
+    }
+
+    fn token_endpoint(&self) -> String {
+        format!("{}/login/oauth/access_token", self.oauth_url)
+    }
+
+    fn api(&self, path: &str, access_token: &str) -> RequestBuilder {
+        Client::new()
+            .get(format!("{}{path}", self.api_url))
+            .bearer_auth(access_token)
+            .header(header::ACCEPT, "application/vnd.github+json")
+            .header(header::USER_AGENT, USER_AGENT)
+    }
+
+    async fn request_tokens(&self, query: &[(&str, &str)]) -> Result<OAuthTokens, AppError> {
+        let response = Client::new()
+            .post(self.token_endpoint())
+            .header(header::ACCEPT, "application/json")
+            .form(query)
+            .send()
+            .await
+            .map_err(AppError::unauthorized)?;
+        response.json().await.map_err(AppError::unauthorized)
+    }
+
+    async fn fetch_primary_email(&self, access_token: &str) -> Result<Option<String>, AppError> {
+        let response = self
+            .api("/user/emails", access_token)
+            .send()
+            .await
+            .map_err(AppError::not_found)?;
+        let emails: Vec<GithubEmail> = response.json().await.map_err(AppError::unauthorized)?;
+        Ok(emails
+            .into_iter()
+            .find(|email| email.primary && email.verified)
+            .map(|email| email.email))
+    }
+}
+
+#[async_trait]
+impl OAuthProvider for Github {
+    fn provider(&self) -> Provider {
+        Provider::GITHUB
+    }
+
+    async fn authorize_url(&self, state: &LinkState) -> Result<String, AppError> {
+        let scope = GITHUB_SCOPES.join(" ");
+        let code_challenge = state.code_challenge();
+        let query = [
+            ("client_id", self.client_id.as_str()),
+            ("redirect_uri", state.redirect.as_str()),
+            ("scope", scope.as_str()),
+            ("state", state.id.as_str()),
+            ("allow_signup", "true"),
+            ("code_challenge", code_challenge.as_str()),
+            ("code_challenge_method", "S256"),
+        ];
+        let url =
+            Url::parse_with_params(&format!("{}/login/oauth/authorize", self.oauth_url), query)
+                .map_err(AppError::bad_request)?;
+        Ok(url.to_string())
+    }
+
+    async fn exchange_code(&self, code: &str, state: &LinkState) -> Result<OAuthTokens, AppError> {
+        self.request_tokens(&[
+            ("client_id", self.client_id.as_str()),
+            ("client_secret", self.client_secret.as_str()),
+            ("redirect_uri", state.redirect.as_str()),
+            ("code", code),
+            ("code_verifier", state.code_verifier.expose()),
+        ])
+        .await
+    }
+
+    async fn fetch_user_info(
+        &self,
+        tokens: &OAuthTokens,
+        _state: &LinkState,
+    ) -> Result<Identity, AppError> {
+        let access_token = tokens.access_token.expose();
+        let response = self
+            .api("/user", access_token)
+            .send()
+            .await
+            .map_err(AppError::not_found)?;
+        let mut info: GithubUserInfo = response.json().await.map_err(AppError::unauthorized)?;
+        // the profile email is only set when the user made it public
+        if info.email.is_none() {
+            info.email = self.fetch_primary_email(access_token).await?;
+        }
+        Ok(Identity::Github(info))
+    }
+
+    async fn refresh_tokens(&self, refresh_token: &str) -> Result<OAuthTokens, AppError> {
+        // only issued to github apps with expiring user tokens enabled
+        if refresh_token.is_empty() {
+            return Err(AppError::bad_request("github login has no refresh token"));
+        }
+        self.request_tokens(&[
+            ("client_id", self.client_id.as_str()),
+            ("client_secret", self.client_secret.as_str()),
+            ("refresh_token", refresh_token),
+            ("grant_type", "refresh_token"),
+        ])
+        .await
+    }
+}
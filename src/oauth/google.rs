@@ -1,28 +1,21 @@
+use async_trait::async_trait;
 use reqwest::{Client, Url};
-use types::{GoogleAccessToken, GoogleRefreshToken, GoogleUserInfo};
+use types::GoogleUserInfo;
 
-use crate::{errors::AppError, models::oauth_link_state::LinkState};
+use crate::{
+    errors::AppError,
+    models::oauth_link_state::{LinkState, Provider},
+};
+
+use super::{
+    oidc::IdTokenVerifier,
+    types::{Identity, OAuthTokens},
+    OAuthProvider,
+};
 
 pub mod types {
     use serde::{Deserialize, Serialize};
 
-    #[derive(Debug, Deserialize, Serialize, Clone, Default)]
-    pub struct GoogleAccessToken {
-        pub access_token: String,
-        pub expires_in: u32, // seconds
-        pub token_type: String,
-        pub scope: String,
-        pub refresh_token: String,
-    }
-
-    #[derive(Debug, Deserialize, Serialize, Clone, Default)]
-    pub struct GoogleRefreshToken {
-        pub access_token: String,
-        pub expires_in: u32, // seconds
-        pub token_type: String,
-        pub scope: String,
-    }
-
     #[derive(Debug, Deserialize, Serialize, Clone, Default)]
     pub struct GoogleUserInfo {
         pub id: String,
@@ -36,84 +29,143 @@ pub mod types {
 }
 
 const GOOGLE_OAUTH_ENDPOINT: &str = "https://accounts.google.com/o/oauth2/v2/auth";
-const GOOGLE_TOKEN_ENDPOINT: &str = " https://oauth2.googleapis.com/token";
-const GOOGLE_USER_INFO_ENDPOINT: &str = " https://www.googleapis.com/oauth2/v1/userinfo";
+const GOOGLE_TOKEN_ENDPOINT: &str = "https://oauth2.googleapis.com/token";
+const GOOGLE_USER_INFO_ENDPOINT: &str = "https://www.googleapis.com/oauth2/v1/userinfo";
+const GOOGLE_REVOKE_ENDPOINT: &str = "https://oauth2.googleapis.com/revoke";
+const GOOGLE_JWKS_ENDPOINT: &str = "https://www.googleapis.com/oauth2/v3/certs";
+// google documents both forms of its id_token issuer
+const GOOGLE_ISSUERS: [&str; 2] = ["https://accounts.google.com", "accounts.google.com"];
 const GOOGLE_SCOPES: [&str; 3] = [
     "openid",
     "https://www.googleapis.com/auth/userinfo.email",
     "https://www.googleapis.com/auth/userinfo.profile",
 ];
 
-pub async fn build_oauth_link(client_id: &str, state: &LinkState) -> Result<String, AppError> {
-    let query = [
-        ("client_id", client_id.to_string()),
-        ("access_type", "offline".to_string()),
-        ("redirect_uri", state.redirect.to_string()),
-        ("response_type", "code".to_string()),
-        ("prompt", "consent".to_string()),
-        ("state", state.id.to_string()),
-        ("scope", GOOGLE_SCOPES.join(" ")),
-        ("include_granted_scopes", "true".to_string()),
-    ];
-    let mut url = Url::parse(GOOGLE_OAUTH_ENDPOINT).map_err(AppError::bad_request)?;
-    let query_string = query
-        .iter()
-        .map(|(key, value)| format!("{key}={value}"))
-        .collect::<Vec<_>>()
-        .join("&");
-    url.set_query(Some(&query_string));
-    Ok(url.to_string())
+pub struct Google {
+    client_id: String,
+    client_secret: String,
+    id_tokens: IdTokenVerifier,
 }
 
-pub async fn handle_callback(
-    client_id: &str,
-    client_secret: &str,
-    code: &str,
-    redirect: &str,
-) -> Result<GoogleAccessToken, AppError> {
-    let query = [
-        ("client_id", client_id),
-        ("client_secret", client_secret),
-        ("redirect_uri", redirect),
-        ("grant_type", "authorization_code"),
-        ("code", code),
-    ];
-    let response = Client::new()
-        .post(GOOGLE_TOKEN_ENDPOINT)
-        .form(&query)
-        .send()
-        .await
-        .map_err(AppError::unauthorized)?;
-    response.json().await.map_err(AppError::unauthorized)
+impl Google {
+    pub fn new(client_id: &str, client_secret: &str) -> Self {
+        Self {
+            client_id: client_id.to_string(),
+            client_secret: client_secret.to_string(),
+            id_tokens: IdTokenVerifier::new(&GOOGLE_ISSUERS, client_id, GOOGLE_JWKS_ENDPOINT),
+        }
+    }
 }
 
-pub async fn fetch_user_info(access_token: &str) -> Result<GoogleUserInfo, AppError> {
-    let query = [("alt", "json"), ("access_token", access_token)];
-    let response = Client::new()
-        .get(GOOGLE_USER_INFO_ENDPOINT)
-        .query(&query)
-        .send()
-        .await
-        .map_err(AppError::not_found)?;
-    response.json().await.map_err(AppError::unauthorized)
-}
+#[async_trait]
+impl OAuthProvider for Google {
+    fn provider(&self) -> Provider {
+        Provider::GOOGLE
+    }
 
-pub async fn refresh_tokens(
-    client_id: &str,
-    client_secret: &str,
-    refresh_token: &str,
-) -> Result<GoogleRefreshToken, AppError> {
-    let query = [
-        ("client_id", client_id),
-        ("client_secret", client_secret),
-        ("refresh_token", refresh_token),
-        ("grant_type", "refresh_token"),
-    ];
-    let response = Client::new()
-        .post(GOOGLE_TOKEN_ENDPOINT)
-        .form(&query)
-        .send()
-        .await
-        .map_err(AppError::not_found)?;
-    response.json().await.map_err(AppError::unauthorized)
+    async fn authorize_url(&self, state: &LinkState) -> Result<String, AppError> {
+        let mut query = vec![
+            ("client_id", self.client_id.to_string()),
+            ("access_type", "offline".to_string()),
+            ("redirect_uri", state.redirect.to_string()),
+            ("response_type", "code".to_string()),
+            ("prompt", "consent".to_string()),
+            ("state", state.id.to_string()),
+            ("scope", GOOGLE_SCOPES.join(" ")),
+            ("include_granted_scopes", "true".to_string()),
+            ("code_challenge", state.code_challenge()),
+            ("code_challenge_method", "S256".to_string()),
+        ];
+        if let Some(nonce) = &state.nonce {
+            query.push(("nonce", nonce.to_string()));
+        }
+        let url =
+            Url::parse_with_params(GOOGLE_OAUTH_ENDPOINT, query).map_err(AppError::bad_request)?;
+        Ok(url.to_string())
+    }
+
+    async fn exchange_code(&self, code: &str, state: &LinkState) -> Result<OAuthTokens, AppError> {
+        let query = [
+            ("client_id", self.client_id.as_str()),
+            ("client_secret", self.client_secret.as_str()),
+            ("redirect_uri", state.redirect.as_str()),
+            ("grant_type", "authorization_code"),
+            ("code", code),
+            ("code_verifier", state.code_verifier.expose()),
+        ];
+        let response = Client::new()
+            .post(GOOGLE_TOKEN_ENDPOINT)
+            .form(&query)
+            .send()
+            .await
+            .map_err(AppError::unauthorized)?;
+        response.json().await.map_err(AppError::unauthorized)
+    }
+
+    async fn fetch_user_info(
+        &self,
+        tokens: &OAuthTokens,
+        state: &LinkState,
+    ) -> Result<Identity, AppError> {
+        let id_token = tokens
+            .id_token
+            .as_ref()
+            .ok_or_else(|| AppError::unauthorized("token response is missing an id_token"))?;
+        let claims = self
+            .id_tokens
+            .verify(id_token.expose(), state.nonce.as_deref())
+            .await?;
+        let query = [
+            ("alt", "json"),
+            ("access_token", tokens.access_token.expose()),
+        ];
+        let response = Client::new()
+            .get(GOOGLE_USER_INFO_ENDPOINT)
+            .query(&query)
+            .send()
+            .await
+            .map_err(AppError::not_found)?;
+        let info: GoogleUserInfo = response.json().await.map_err(AppError::unauthorized)?;
+        if info.id != claims.sub {
+            return Err(AppError::unauthorized("userinfo subject mismatch"));
+        }
+        Ok(Identity::Google(info))
+    }
+
+    async fn refresh_tokens(&self, refresh_token: &str) -> Result<OAuthTokens, AppError> {
+        let query = [
+            ("client_id", self.client_id.as_str()),
+            ("client_secret", self.client_secret.as_str()),
+            ("refresh_token", refresh_token),
+            ("grant_type", "refresh_token"),
+        ];
+        let response = Client::new()
+            .post(GOOGLE_TOKEN_ENDPOINT)
+            .form(&query)
+            .send()
+            .await
+            .map_err(AppError::not_found)?;
+        response.json().await.map_err(AppError::unauthorized)
+    }
+
+    async fn revoke(&self, tokens: &OAuthTokens) -> Result<(), AppError> {
+        // revoking the refresh token ends the whole grant, access tokens included
+        let token = match tokens.refresh_token.expose().as_str() {
+            "" => tokens.access_token.expose(),
+            refresh_token => refresh_token,
+        };
+        let response = Client::new()
+            .post(GOOGLE_REVOKE_ENDPOINT)
+            .form(&[("token", token)])
+            .send()
+            .await
+            .map_err(AppError::internal_server_error)?;
+        if !response.status().is_success() {
+            return Err(AppError::bad_request(format!(
+                "google token revocation failed: {}",
+                response.status()
+            )));
+        }
+        Ok(())
+    }
 }
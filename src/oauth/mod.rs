@@ -1,16 +1,211 @@
+use async_trait::async_trait;
+use std::{collections::HashMap, fmt, sync::Arc};
+use types::{Identity, OAuthTokens};
+
+use crate::{
+    env::Env,
+    errors::AppError,
+    models::oauth_link_state::{LinkState, Provider},
+};
+
+pub mod discord;
+pub mod github;
 pub mod google;
+pub mod oidc;
 
 pub mod types {
+    use bson::Bson;
     use serde::{Deserialize, Serialize};
+    use std::collections::BTreeMap;
+
+    use crate::{errors::AppError, models::oauth_link_state::Provider, secret::Secret};
+
+    use super::{
+        discord::types::DiscordUserInfo, github::types::GithubUserInfo,
+        google::types::GoogleUserInfo, oidc::types::OidcUserInfo,
+    };
+
+    // provider slug -> authorization link
+    pub type Links = BTreeMap<String, String>;
 
-    #[derive(Debug, Serialize)]
-    pub struct Links {
-        pub google: String,
+    #[derive(Debug, Deserialize, Default)]
+    pub struct LinkQuery {
+        pub return_to: Option<String>,
     }
 
     #[derive(Debug, Deserialize)]
-    pub struct GoogleOauthCallback {
+    pub struct CodeExchange {
         pub code: String,
+    }
+
+    /// query the provider redirects back with: a `code`, or an `error` such as `access_denied`
+    #[derive(Debug, Deserialize)]
+    pub struct OAuthCallback {
+        pub code: Option<String>,
         pub state: String,
+        pub error: Option<String>,
+    }
+
+    /// token endpoint response, shared by every provider
+    #[derive(Debug, Deserialize, Serialize, Clone, Default)]
+    pub struct OAuthTokens {
+        #[serde(serialize_with = "crate::secret::serialize")]
+        pub access_token: Secret<String>,
+        #[serde(default)]
+        pub expires_in: u32, // seconds
+        pub token_type: String,
+        #[serde(default)]
+        pub scope: String,
+        // not every grant returns one; empty when missing
+        #[serde(default, serialize_with = "crate::secret::serialize")]
+        pub refresh_token: Secret<String>,
+        // only needed while signing in, never stored
+        #[serde(default, skip_serializing)]
+        pub id_token: Option<Secret<String>>,
+    }
+
+    /// the user a provider's access token belongs to
+    #[derive(Debug, Clone)]
+    pub enum Identity {
+        Google(GoogleUserInfo),
+        Github(GithubUserInfo),
+        Discord(DiscordUserInfo),
+        Oidc(String, OidcUserInfo), // configured provider name
+    }
+
+    impl Identity {
+        pub fn provider(&self) -> Provider {
+            match self {
+                Self::Google(_) => Provider::GOOGLE,
+                Self::Github(_) => Provider::GITHUB,
+                Self::Discord(_) => Provider::DISCORD,
+                Self::Oidc(name, _) => Provider::OIDC(name.to_string()),
+            }
+        }
+
+        /// the provider's own, stable id for the user, typed as it is stored
+        pub fn id(&self) -> Bson {
+            match self {
+                Self::Google(info) => Bson::String(info.id.to_string()),
+                Self::Github(info) => Bson::Int64(info.id),
+                Self::Discord(info) => Bson::String(info.id.to_string()),
+                Self::Oidc(_, info) => Bson::String(info.sub.to_string()),
+            }
+        }
+
+        /// field of the stored metadata `id` is read from
+        pub const fn id_field(&self) -> &'static str {
+            match self {
+                Self::Oidc(..) => "sub",
+                _ => "id",
+            }
+        }
+
+        /// what the provider calls the user; only used to seed a new user's profile
+        pub fn display_name(&self) -> Option<String> {
+            let name = match self {
+                Self::Google(info) => Some(info.name.to_string()),
+                Self::Github(info) => info.name.clone().or_else(|| Some(info.login.to_string())),
+                Self::Discord(info) => info
+                    .global_name
+                    .clone()
+                    .or_else(|| Some(info.username.to_string())),
+                Self::Oidc(_, info) => info
+                    .name
+                    .clone()
+                    .or_else(|| info.preferred_username.clone()),
+            };
+            name.map(|name| name.trim().to_string())
+                .filter(|name| !name.is_empty())
+        }
+
+        pub fn metadata(&self) -> Result<Bson, AppError> {
+            match self {
+                Self::Google(info) => bson::to_bson(info),
+                Self::Github(info) => bson::to_bson(info),
+                Self::Discord(info) => bson::to_bson(info),
+                Self::Oidc(_, info) => bson::to_bson(info),
+            }
+            .map_err(AppError::internal_server_error)
+        }
+    }
+}
+
+#[async_trait]
+pub trait OAuthProvider: Send + Sync {
+    fn provider(&self) -> Provider;
+
+    async fn authorize_url(&self, state: &LinkState) -> Result<String, AppError>;
+
+    async fn exchange_code(&self, code: &str, state: &LinkState) -> Result<OAuthTokens, AppError>;
+
+    async fn fetch_user_info(
+        &self,
+        tokens: &OAuthTokens,
+        state: &LinkState,
+    ) -> Result<Identity, AppError>;
+
+    async fn refresh_tokens(&self, refresh_token: &str) -> Result<OAuthTokens, AppError>;
+
+    /// withdraws our access at the provider; a no-op for providers without a revocation endpoint
+    async fn revoke(&self, _tokens: &OAuthTokens) -> Result<(), AppError> {
+        Ok(())
+    }
+}
+
+/// every provider configured for this deployment
+#[derive(Clone, Default)]
+pub struct Registry {
+    providers: HashMap<Provider, Arc<dyn OAuthProvider>>,
+}
+
+impl fmt::Debug for Registry {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_list().entries(self.providers.keys()).finish()
+    }
+}
+
+impl Registry {
+    pub fn from_env(env: &Env) -> Self {
+        let mut registry = Self::default();
+        registry.register(google::Google::new(
+            &env.google_client_id,
+            &env.google_client_secret,
+        ));
+        if let (Some(client_id), Some(client_secret)) =
+            (&env.github_client_id, &env.github_client_secret)
+        {
+            registry.register(github::Github::new(
+                client_id,
+                client_secret,
+                &env.github_oauth_url,
+                &env.github_api_url,
+            ));
+        }
+        if let (Some(client_id), Some(client_secret)) =
+            (&env.discord_client_id, &env.discord_client_secret)
+        {
+            registry.register(discord::Discord::new(client_id, client_secret));
+        }
+        for config in &env.oidc_providers {
+            registry.register(oidc::Oidc::new(config.clone()));
+        }
+        registry
+    }
+
+    pub fn register(&mut self, provider: impl OAuthProvider + 'static) {
+        self.providers
+            .insert(provider.provider(), Arc::new(provider));
+    }
+
+    pub fn get(&self, provider: &Provider) -> Result<Arc<dyn OAuthProvider>, AppError> {
+        self.providers
+            .get(provider)
+            .cloned()
+            .ok_or_else(|| AppError::not_found(format!("{provider} login is not enabled")))
+    }
+
+    pub fn providers(&self) -> impl Iterator<Item = &Arc<dyn OAuthProvider>> {
+        self.providers.values()
     }
 }
@@ -0,0 +1,355 @@
+use async_trait::async_trait;
+use jsonwebtoken::{
+    decode, decode_header,
+    jwk::{Jwk, JwkSet},
+    Algorithm, DecodingKey, Validation,
+};
+use reqwest::{Client, Url};
+use std::{
+    str::FromStr,
+    sync::RwLock,
+    time::{Duration, Instant},
+};
+use tokio::sync::{Mutex, OnceCell};
+use types::{Discovery, IdTokenClaims, OidcConfig, OidcUserInfo};
+
+use crate::{
+    errors::AppError,
+    models::oauth_link_state::{LinkState, Provider},
+};
+
+use super::{
+    types::{Identity, OAuthTokens},
+    OAuthProvider,
+};
+
+pub mod types {
+    use serde::{Deserialize, Serialize};
+
+    fn default_scopes() -> Vec<String> {
+        vec![
+            "openid".to_string(),
+            "email".to_string(),
+            "profile".to_string(),
+        ]
+    }
+
+    /// one entry of `OIDC_PROVIDERS`
+    #[derive(Debug, Deserialize, Clone)]
+    pub struct OidcConfig {
+        pub name: String, // route and storage key, e.g. `/oauth/okta-redirect`
+        pub issuer: String,
+        pub client_id: String,
+        pub client_secret: String,
+        #[serde(default = "default_scopes")]
+        pub scopes: Vec<String>,
+    }
+
+    /// the subset of `.well-known/openid-configuration` the flow needs
+    #[derive(Debug, Deserialize, Clone)]
+    pub struct Discovery {
+        pub issuer: String,
+        pub authorization_endpoint: String,
+        pub token_endpoint: String,
+        pub userinfo_endpoint: Option<String>,
+        pub jwks_uri: String,
+        #[serde(default)]
+        pub token_endpoint_auth_methods_supported: Vec<String>,
+    }
+
+    #[derive(Debug, Deserialize, Serialize, Clone, Default)]
+    pub struct OidcUserInfo {
+        pub sub: String,
+        pub email: Option<String>,
+        pub email_verified: Option<bool>,
+        pub name: Option<String>,
+        pub preferred_username: Option<String>,
+        pub picture: Option<String>,
+    }
+
+    #[derive(Debug, Deserialize)]
+    pub struct IdTokenClaims {
+        #[serde(flatten)]
+        pub user: OidcUserInfo,
+        pub nonce: Option<String>,
+    }
+}
+
+// signing algorithms an identity provider may use; shared-secret HMAC is never accepted
+const ALLOWED_ALGORITHMS: [Algorithm; 8] = [
+    Algorithm::RS256,
+    Algorithm::RS384,
+    Algorithm::RS512,
+    Algorithm::PS256,
+    Algorithm::PS384,
+    Algorithm::PS512,
+    Algorithm::ES256,
+    Algorithm::EdDSA,
+];
+
+// an unknown `kid` refetches the JWKS at most this often, so forged headers can't hammer the issuer
+const JWKS_REFETCH_INTERVAL: Duration = Duration::from_secs(60);
+// discovery runs while building `GET /oauth` links, so a dead issuer must fail fast
+const DISCOVERY_TIMEOUT: Duration = Duration::from_secs(5);
+
+struct CachedJwks {
+    keys: JwkSet,
+    fetched_at: Instant,
+}
+
+/// checks `id_token` signatures against the issuer's JWKS, plus `iss`, `aud`, `exp` and `nonce`
+pub struct IdTokenVerifier {
+    issuers: Vec<String>,
+    audience: String,
+    jwks_uri: String,
+    jwks: RwLock<Option<CachedJwks>>,
+    refetch: Mutex<()>, // one refetch at a time
+}
+
+impl IdTokenVerifier {
+    pub fn new(issuers: &[&str], audience: &str, jwks_uri: &str) -> Self {
+        Self {
+            issuers: issuers.iter().map(ToString::to_string).collect(),
+            audience: audience.to_string(),
+            jwks_uri: jwks_uri.to_string(),
+            jwks: RwLock::new(None),
+            refetch: Mutex::new(()),
+        }
+    }
+
+    /// whether `jwk` may verify `alg` tokens; keys that don't declare an alg may verify any
+    fn signs_with(jwk: &Jwk, alg: Algorithm) -> bool {
+        jwk.common.key_algorithm.map_or(true, |key_algorithm| {
+            Algorithm::from_str(&key_algorithm.to_string()).ok() == Some(alg)
+        })
+    }
+
+    /// the key `kid` names or, for tokens without a kid, the only key for `alg`
+    fn select(jwks: &JwkSet, kid: Option<&str>, alg: Algorithm) -> Option<Jwk> {
+        if let Some(kid) = kid {
+            return jwks.find(kid).cloned();
+        }
+        let mut candidates = jwks.keys.iter().filter(|jwk| Self::signs_with(jwk, alg));
+        match (candidates.next(), candidates.next()) {
+            (Some(jwk), None) => Some(jwk.clone()),
+            _ => None,
+        }
+    }
+
+    fn cached_key(&self, kid: Option<&str>, alg: Algorithm) -> Result<Option<Jwk>, AppError> {
+        let jwks = self.jwks.read().map_err(AppError::internal_server_error)?;
+        Ok(jwks
+            .as_ref()
+            .and_then(|jwks| Self::select(&jwks.keys, kid, alg)))
+    }
+
+    fn fetched_recently(&self) -> Result<bool, AppError> {
+        let jwks = self.jwks.read().map_err(AppError::internal_server_error)?;
+        Ok(jwks
+            .as_ref()
+            .is_some_and(|jwks| jwks.fetched_at.elapsed() < JWKS_REFETCH_INTERVAL))
+    }
+
+    async fn find_key(&self, kid: Option<&str>, alg: Algorithm) -> Result<Jwk, AppError> {
+        if let Some(jwk) = self.cached_key(kid, alg)? {
+            return Ok(jwk);
+        }
+        let _refetch = self.refetch.lock().await;
+        // another request may have refetched while this one waited
+        if let Some(jwk) = self.cached_key(kid, alg)? {
+            return Ok(jwk);
+        }
+        if self.fetched_recently()? {
+            return Err(AppError::unauthorized(
+                "id_token signed with an unknown key",
+            ));
+        }
+        // unknown kid: the issuer may have rotated, so refetch
+        let response = Client::new()
+            .get(&self.jwks_uri)
+            .send()
+            .await
+            .map_err(AppError::unauthorized)?;
+        let jwks: JwkSet = response.json().await.map_err(AppError::unauthorized)?;
+        let jwk = Self::select(&jwks, kid, alg);
+        *self.jwks.write().map_err(AppError::internal_server_error)? = Some(CachedJwks {
+            keys: jwks,
+            fetched_at: Instant::now(),
+        });
+        jwk.ok_or_else(|| AppError::unauthorized("id_token signed with an unknown key"))
+    }
+
+    pub async fn verify(
+        &self,
+        id_token: &str,
+        nonce: Option<&str>,
+    ) -> Result<OidcUserInfo, AppError> {
+        let header = decode_header(id_token).map_err(AppError::unauthorized)?;
+        if !ALLOWED_ALGORITHMS.contains(&header.alg) {
+            return Err(AppError::unauthorized("id_token algorithm not allowed"));
+        }
+        let jwk = self.find_key(header.kid.as_deref(), header.alg).await?;
+        if !Self::signs_with(&jwk, header.alg) {
+            return Err(AppError::unauthorized(
+                "id_token algorithm does not match key",
+            ));
+        }
+        let key = DecodingKey::from_jwk(&jwk).map_err(AppError::unauthorized)?;
+        let mut validation = Validation::new(header.alg);
+        validation.set_issuer(&self.issuers);
+        validation.set_audience(&[&self.audience]);
+        validation.set_required_spec_claims(&["exp", "iss", "aud", "sub"]);
+        let claims = decode::<IdTokenClaims>(id_token, &key, &validation)
+            .map_err(AppError::unauthorized)?
+            .claims;
+        if claims.nonce.as_deref() != nonce {
+            return Err(AppError::unauthorized("id_token nonce mismatch"));
+        }
+        Ok(claims.user)
+    }
+}
+
+/// any `OpenID Connect` provider, configured entirely from its issuer url
+pub struct Oidc {
+    config: OidcConfig,
+    discovery: OnceCell<(Discovery, IdTokenVerifier)>,
+}
+
+impl Oidc {
+    pub fn new(config: OidcConfig) -> Self {
+        Self {
+            config,
+            discovery: OnceCell::new(),
+        }
+    }
+
+    async fn discover(&self) -> Result<&(Discovery, IdTokenVerifier), AppError> {
+        self.discovery
+            .get_or_try_init(|| async {
+                let url = format!(
+                    "{}/.well-known/openid-configuration",
+                    self.config.issuer.trim_end_matches('/')
+                );
+                let response = Client::new()
+                    .get(url)
+                    .timeout(DISCOVERY_TIMEOUT)
+                    .send()
+                    .await
+                    .map_err(AppError::internal_server_error)?;
+                let discovery: Discovery = response
+                    .json()
+                    .await
+                    .map_err(AppError::internal_server_error)?;
+                if discovery.issuer.trim_end_matches('/')
+                    != self.config.issuer.trim_end_matches('/')
+                {
+                    return Err(AppError::internal_server_error(format!(
+                        "{} discovery issuer mismatch",
+                        self.config.name
+                    )));
+                }
+                let verifier = IdTokenVerifier::new(
+                    &[discovery.issuer.as_str()],
+                    &self.config.client_id,
+                    &discovery.jwks_uri,
+                );
+                Ok((discovery, verifier))
+            })
+            .await
+    }
+
+    async fn request_tokens(&self, query: &[(&str, &str)]) -> Result<OAuthTokens, AppError> {
+        let (discovery, _) = self.discover().await?;
+        let methods = &discovery.token_endpoint_auth_methods_supported;
+        // client_secret_basic is the spec default when the provider doesn't say
+        let request = Client::new().post(&discovery.token_endpoint);
+        let request = if methods.is_empty() || methods.iter().any(|m| m == "client_secret_basic") {
+            request
+                .basic_auth(&self.config.client_id, Some(&self.config.client_secret))
+                .form(query)
+        } else {
+            let mut form = query.to_vec();
+            form.push(("client_id", &self.config.client_id));
+            form.push(("client_secret", &self.config.client_secret));
+            request.form(&form)
+        };
+        let response = request.send().await.map_err(AppError::unauthorized)?;
+        response.json().await.map_err(AppError::unauthorized)
+    }
+}
+
+#[async_trait]
+impl OAuthProvider for Oidc {
+    fn provider(&self) -> Provider {
+        Provider::OIDC(self.config.name.to_string())
+    }
+
+    async fn authorize_url(&self, state: &LinkState) -> Result<String, AppError> {
+        let (discovery, _) = self.discover().await?;
+        let scope = self.config.scopes.join(" ");
+        let code_challenge = state.code_challenge();
+        let mut query = vec![
+            ("client_id", self.config.client_id.as_str()),
+            ("redirect_uri", state.redirect.as_str()),
+            ("response_type", "code"),
+            ("scope", scope.as_str()),
+            ("state", state.id.as_str()),
+            ("code_challenge", code_challenge.as_str()),
+            ("code_challenge_method", "S256"),
+        ];
+        if let Some(nonce) = &state.nonce {
+            query.push(("nonce", nonce.as_str()));
+        }
+        let url = Url::parse_with_params(&discovery.authorization_endpoint, query)
+            .map_err(AppError::bad_request)?;
+        Ok(url.to_string())
+    }
+
+    async fn exchange_code(&self, code: &str, state: &LinkState) -> Result<OAuthTokens, AppError> {
+        self.request_tokens(&[
+            ("grant_type", "authorization_code"),
+            ("code", code),
+            ("redirect_uri", state.redirect.as_str()),
+            ("code_verifier", state.code_verifier.expose()),
+        ])
+        .await
+    }
+
+    async fn fetch_user_info(
+        &self,
+        tokens: &OAuthTokens,
+        state: &LinkState,
+    ) -> Result<Identity, AppError> {
+        let (discovery, verifier) = self.discover().await?;
+        let id_token = tokens
+            .id_token
+            .as_ref()
+            .ok_or_else(|| AppError::unauthorized("token response is missing an id_token"))?;
+        let mut info = verifier
+            .verify(id_token.expose(), state.nonce.as_deref())
+            .await?;
+        // id_tokens are often minimal; fill in the profile from userinfo when offered
+        if let Some(userinfo_endpoint) = &discovery.userinfo_endpoint {
+            let response = Client::new()
+                .get(userinfo_endpoint)
+                .bearer_auth(tokens.access_token.expose())
+                .send()
+                .await
+                .map_err(AppError::not_found)?;
+            let profile: OidcUserInfo = response.json().await.map_err(AppError::unauthorized)?;
+            if profile.sub != info.sub {
+                return Err(AppError::unauthorized("userinfo subject mismatch"));
+            }
+            info = profile;
+        }
+        Ok(Identity::Oidc(self.config.name.to_string(), info))
+    }
+
+    async fn refresh_tokens(&self, refresh_token: &str) -> Result<OAuthTokens, AppError> {
+        self.request_tokens(&[
+            ("grant_type", "refresh_token"),
+            ("refresh_token", refresh_token),
+        ])
+        .await
+    }
+}
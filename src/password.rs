@@ -0,0 +1,69 @@
+use argon2::{
+    password_hash::{rand_core::OsRng, PasswordHash, PasswordHasher, PasswordVerifier, SaltString},
+    Algorithm, Argon2, Params, Version,
+};
+use std::sync::OnceLock;
+
+use crate::errors::AppError;
+
+fn hasher() -> Argon2<'static> {
+    Argon2::new(Algorithm::Argon2id, Version::V0x13, Params::default())
+}
+
+/// hashes with argon2id using a fresh random salt, returning a PHC string
+/// (`$argon2id$v=19$m=...,t=...,p=...$salt$hash`) so parameters travel with the hash
+pub fn hash(password: &str) -> Result<String, AppError> {
+    let salt = SaltString::generate(&mut OsRng);
+    let hashed = hasher()
+        .hash_password(password.as_bytes(), &salt)
+        .map_err(AppError::internal_server_error)?;
+    Ok(hashed.to_string())
+}
+
+/// constant time comparison of `password` against an encoded hash.
+/// uses the parameters stored in the hash, not the current defaults
+pub fn verify(password: &str, encoded: &str) -> bool {
+    let Ok(parsed) = PasswordHash::new(encoded) else {
+        tracing::error!("[ERROR]: stored password hash could not be parsed");
+        return false;
+    };
+    hasher()
+        .verify_password(password.as_bytes(), &parsed)
+        .is_ok()
+}
+
+/// `verify`, as the error a login returns for a wrong password
+pub fn check(password: &str, encoded: &str) -> Result<(), AppError> {
+    if !verify(password, encoded) {
+        return Err(AppError::unauthorized("invalid username or password"));
+    }
+    Ok(())
+}
+
+/// burns the same amount of work as a real verification,
+/// so unknown usernames can't be told apart by response time
+pub fn verify_dummy(password: &str) {
+    static DUMMY: OnceLock<Option<String>> = OnceLock::new();
+    if let Some(encoded) = DUMMY.get_or_init(|| hash("dummy-password").ok()) {
+        verify(password, encoded);
+    }
+}
+
+/// true when the hash was produced with an older algorithm, version or cost parameters
+pub fn needs_rehash(encoded: &str) -> bool {
+    let Ok(parsed) = PasswordHash::new(encoded) else {
+        return true;
+    };
+    if parsed.algorithm != Algorithm::Argon2id.ident()
+        || parsed.version != Some(Version::V0x13.into())
+    {
+        return true;
+    }
+    let Ok(params) = Params::try_from(&parsed) else {
+        return true;
+    };
+    let current = Params::default();
+    params.m_cost() != current.m_cost()
+        || params.t_cost() != current.t_cost()
+        || params.p_cost() != current.p_cost()
+}
@@ -0,0 +1,72 @@
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeSet, fmt, str::FromStr};
+
+use crate::errors::AppError;
+
+/// a single action a user may be allowed to take
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Permission {
+    #[serde(rename = "users:read")]
+    ReadUsers,
+    #[serde(rename = "users:write")]
+    WriteUsers,
+    #[serde(rename = "sessions:revoke")]
+    RevokeSessions,
+    #[serde(rename = "roles:write")]
+    WriteRoles,
+}
+
+/// a named bundle of permissions. roles live on the per-service `User`, so they
+/// only ever apply to users of that same service
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    Admin,
+    Support,
+}
+
+impl Role {
+    pub const fn permissions(self) -> &'static [Permission] {
+        match self {
+            Self::Admin => &[
+                Permission::ReadUsers,
+                Permission::WriteUsers,
+                Permission::RevokeSessions,
+                Permission::WriteRoles,
+            ],
+            Self::Support => &[Permission::ReadUsers, Permission::RevokeSessions],
+        }
+    }
+}
+
+impl FromStr for Role {
+    type Err = AppError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_lowercase().as_str() {
+            "admin" => Ok(Self::Admin),
+            "support" => Ok(Self::Support),
+            other => Err(AppError::bad_request(format!("unknown role: {other}"))),
+        }
+    }
+}
+
+impl fmt::Display for Role {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Admin => write!(f, "admin"),
+            Self::Support => write!(f, "support"),
+        }
+    }
+}
+
+/// permissions granted by `roles` plus any granted directly
+pub fn effective_permissions(roles: &[Role], granted: &[Permission]) -> Vec<Permission> {
+    roles
+        .iter()
+        .flat_map(|role| role.permissions().iter().copied())
+        .chain(granted.iter().copied())
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .collect()
+}
@@ -0,0 +1,51 @@
+use serde::{Deserialize, Serialize, Serializer};
+use std::fmt;
+
+/// A value that must never leave the server: password hashes, provider tokens, etc.
+///
+/// `Secret` deliberately does not implement `Serialize`. Models that need to
+/// persist one opt in per field with
+/// `#[serde(serialize_with = "crate::secret::serialize")]`, so any response type
+/// that picks up a secret field without that annotation fails to compile:
+///
+/// ```compile_fail
+/// use pixel_collector_api::secret::Secret;
+///
+/// #[derive(serde::Serialize)]
+/// struct Response {
+///     token: Secret<String>,
+/// }
+/// ```
+#[derive(Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(transparent)]
+pub struct Secret<T>(T);
+
+impl<T> Secret<T> {
+    pub const fn new(value: T) -> Self {
+        Self(value)
+    }
+
+    pub const fn expose(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T> From<T> for Secret<T> {
+    fn from(value: T) -> Self {
+        Self(value)
+    }
+}
+
+impl<T> fmt::Debug for Secret<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Secret([REDACTED])")
+    }
+}
+
+/// storage only, never use on a response type
+pub fn serialize<S: Serializer, T: Serialize>(
+    secret: &Secret<T>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    secret.0.serialize(serializer)
+}
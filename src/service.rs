@@ -0,0 +1,186 @@
+use axum::http::{header, HeaderMap};
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, fmt, time::Duration};
+
+use crate::{errors::AppError, models::oauth_link_state::Provider};
+
+pub const SERVICE_HEADER: &str = "X-JUDETHING-SERVICE";
+
+const LOCALHOST: &str = "LOCALHOST";
+
+/// id of a client application, as sent in `X-JUDETHING-SERVICE` and stored on users and tokens
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Eq, Hash)]
+#[serde(transparent)]
+pub struct Service(String);
+
+impl Service {
+    pub fn new(id: &str) -> Self {
+        Self(id.trim().to_uppercase())
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Default for Service {
+    fn default() -> Self {
+        Self::new(LOCALHOST)
+    }
+}
+
+impl fmt::Display for Service {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+const fn default_access_token_ttl() -> u64 {
+    60 * 15
+}
+
+const fn default_refresh_token_ttl() -> u64 {
+    60 * 60 * 24 * 30
+}
+
+/// one client application; an entry of `SERVICES`
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct ClientApp {
+    pub id: Service,
+    pub name: String,
+    #[serde(default)]
+    pub allowed_origins: Vec<String>,
+    // where the oauth callback may send users back to; matched by origin and path prefix
+    #[serde(default)]
+    pub allowed_redirects: Vec<String>,
+    #[serde(default = "default_access_token_ttl")]
+    pub access_token_ttl: u64, // seconds
+    #[serde(default = "default_refresh_token_ttl")]
+    pub refresh_token_ttl: u64, // seconds
+    // provider slugs; empty enables every provider configured for the deployment
+    #[serde(default)]
+    pub providers: Vec<String>,
+}
+
+impl ClientApp {
+    pub fn localhost() -> Self {
+        Self {
+            id: Service::default(),
+            name: "localhost".to_string(),
+            allowed_origins: vec![
+                "http://localhost:3000".to_string(),
+                "http://localhost:5173".to_string(),
+            ],
+            allowed_redirects: vec![
+                "http://localhost:3000".to_string(),
+                "http://localhost:5173".to_string(),
+            ],
+            access_token_ttl: default_access_token_ttl(),
+            refresh_token_ttl: default_refresh_token_ttl(),
+            providers: vec![],
+        }
+    }
+
+    pub const fn access_token_ttl(&self) -> Duration {
+        Duration::from_secs(self.access_token_ttl)
+    }
+
+    pub const fn refresh_token_ttl(&self) -> Duration {
+        Duration::from_secs(self.refresh_token_ttl)
+    }
+
+    pub fn allows_provider(&self, provider: &Provider) -> bool {
+        self.providers.is_empty() || self.providers.iter().any(|slug| slug == provider.slug())
+    }
+
+    pub fn allows_origin(&self, origin: &str) -> bool {
+        self.allowed_origins
+            .iter()
+            .any(|allowed| allowed.trim_end_matches('/') == origin.trim_end_matches('/'))
+    }
+
+    /// whole segments only: `/app` admits `/app` and `/app/callback`, never `/application`
+    fn path_within(path: &str, allowed: &str) -> bool {
+        if path == allowed || allowed.ends_with('/') && path.starts_with(allowed) {
+            return true;
+        }
+        path.strip_prefix(allowed)
+            .is_some_and(|rest| rest.starts_with('/'))
+    }
+
+    pub fn check_redirect(&self, return_to: &str) -> Result<Url, AppError> {
+        let url = Url::parse(return_to).map_err(AppError::bad_request)?;
+        let allowed = self.allowed_redirects.iter().any(|allowed| {
+            Url::parse(allowed).is_ok_and(|allowed| {
+                allowed.origin() == url.origin() && Self::path_within(url.path(), allowed.path())
+            })
+        });
+        if !allowed {
+            return Err(AppError::bad_request(format!(
+                "return_to not allowed for {}: {return_to}",
+                self.id
+            )));
+        }
+        Ok(url)
+    }
+}
+
+/// every client application this deployment accepts
+#[derive(Debug, Clone)]
+pub struct Services {
+    services: HashMap<Service, ClientApp>,
+}
+
+impl Services {
+    pub fn new(configs: Vec<ClientApp>) -> Result<Self, AppError> {
+        let mut services = HashMap::new();
+        for mut config in configs {
+            // ids are matched case-insensitively
+            config.id = Service::new(config.id.as_str());
+            if config.id.as_str().is_empty() {
+                return Err(AppError::env_error("service id cannot be empty"));
+            }
+            if let Some(existing) = services.insert(config.id.clone(), config) {
+                return Err(AppError::env_error(format!(
+                    "duplicate service id: {}",
+                    existing.id
+                )));
+            }
+        }
+        Ok(Self { services })
+    }
+
+    pub fn get(&self, id: &Service) -> Result<&ClientApp, AppError> {
+        self.services
+            .get(id)
+            .ok_or_else(|| AppError::bad_request(format!("unknown service: {id}")))
+    }
+
+    /// resolves the calling application from `X-JUDETHING-SERVICE`, checking `Origin` when a browser sends one
+    pub fn from_headers(&self, headers: &HeaderMap) -> Result<&ClientApp, AppError> {
+        let config = match headers.get(SERVICE_HEADER) {
+            Some(header) => {
+                let id = header.to_str().map_err(AppError::bad_request)?;
+                self.get(&Service::new(id))?
+            }
+            // local tooling rarely sets the header
+            None if cfg!(debug_assertions) => self.get(&Service::default())?,
+            None => {
+                return Err(AppError::bad_request(format!(
+                    "Missing header: '{SERVICE_HEADER}'"
+                )))
+            }
+        };
+        if let Some(origin) = headers.get(header::ORIGIN) {
+            let origin = origin.to_str().map_err(AppError::bad_request)?;
+            if !config.allows_origin(origin) {
+                return Err(AppError::bad_request(format!(
+                    "origin not allowed for {}: {origin}",
+                    config.id
+                )));
+            }
+        }
+        Ok(config)
+    }
+}
@@ -0,0 +1,92 @@
+#[cfg(test)]
+mod env {
+    use std::sync::{Mutex, PoisonError};
+
+    use crate::{
+        env::{Env, Stage, LOCAL_PUBLIC_URL, PROD_PUBLIC_URL},
+        errors::AppError,
+        models::oauth_link_state::Provider,
+        oauth::Registry,
+        service::Service,
+    };
+
+    // the environment is shared by the whole test binary, so the tests here take turns
+    static ENV: Mutex<()> = Mutex::new(());
+
+    fn set(vars: &[(&str, &str)]) {
+        for (key, value) in vars {
+            std::env::set_var(key, value);
+        }
+    }
+
+    fn clear(vars: &[(&str, &str)]) {
+        for (key, _) in vars {
+            std::env::remove_var(key);
+        }
+    }
+
+    #[test]
+    fn empty_values_are_unset() -> Result<(), AppError> {
+        let _turn = ENV.lock().unwrap_or_else(PoisonError::into_inner);
+        let vars = [
+            ("JWT_SECRET", "secret"),
+            ("JWT_SIGNING_KEYS", ""),
+            ("JWT_ACTIVE_KID", " "),
+            ("JWT_SECRET_EXPIRES_AT", ""),
+            ("SERVICES", ""),
+            ("OIDC_PROVIDERS", "\n"),
+        ];
+        set(&vars);
+        let keys = Env::jwt_keys();
+        let services = Env::services();
+        let oidc_providers = Env::oidc_providers();
+        clear(&vars);
+        // falls back to the legacy secret, which signs without a kid
+        assert!(keys?.jwks().keys.is_empty());
+        assert!(services?.get(&Service::default()).is_ok());
+        assert!(oidc_providers?.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn blank_provider_credentials_are_not_registered() -> Result<(), AppError> {
+        let _turn = ENV.lock().unwrap_or_else(PoisonError::into_inner);
+        let vars = [
+            ("STAGE", "local"),
+            ("BUCKET_NAME", "bucket"),
+            ("GOOGLE_CLIENT_ID", "google_client_id"),
+            ("GOOGLE_CLIENT_SECRET", "google_client_secret"),
+            ("JWT_SECRET", "secret"),
+            ("GITHUB_CLIENT_ID", ""),
+            ("GITHUB_CLIENT_SECRET", "github_client_secret"),
+            ("DISCORD_CLIENT_ID", " "),
+            ("DISCORD_CLIENT_SECRET", ""),
+        ];
+        set(&vars);
+        let env = Env::load();
+        clear(&vars);
+        let registry = Registry::from_env(&env?);
+        assert!(registry.get(&Provider::GOOGLE).is_ok());
+        assert!(registry.get(&Provider::GITHUB).is_err());
+        assert!(registry.get(&Provider::DISCORD).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn public_url() -> Result<(), AppError> {
+        let _turn = ENV.lock().unwrap_or_else(PoisonError::into_inner);
+        let stage = Stage::Other("staging".to_string());
+        let blank = [("PUBLIC_URL", " ")];
+        set(&blank);
+        assert_eq!(Env::public_url(&Stage::Local)?, LOCAL_PUBLIC_URL);
+        assert_eq!(Env::public_url(&Stage::Prod)?, PROD_PUBLIC_URL);
+        assert!(Env::public_url(&stage).is_err());
+        set(&[("PUBLIC_URL", "api.example.com")]);
+        assert!(Env::public_url(&Stage::Local).is_err());
+        set(&[("PUBLIC_URL", "https://api.example.com/")]);
+        assert_eq!(Env::public_url(&stage)?, "https://api.example.com");
+        clear(&blank);
+        assert!(Env::public_url(&stage).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,156 @@
+#[cfg(test)]
+mod github {
+    use axum::{
+        extract::State,
+        http::{header, HeaderMap, StatusCode},
+        routing::{get, post},
+        Form, Json, Router,
+    };
+    use serde_json::{json, Value};
+    use std::{
+        collections::HashMap,
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        },
+    };
+
+    use crate::{
+        errors::AppError,
+        models::oauth_link_state::Provider,
+        oauth::{github::Github, types::Identity, OAuthProvider},
+        tests::mock,
+    };
+
+    const ACCESS_TOKEN: &str = "gho_mock_access_token";
+
+    #[derive(Clone)]
+    struct MockGithub {
+        profile_email: Option<&'static str>,
+        email_requests: Arc<AtomicUsize>,
+    }
+
+    async fn access_token(Form(form): Form<HashMap<String, String>>) -> (StatusCode, Json<Value>) {
+        // pkce verifier and code must both reach the token endpoint
+        if form.get("code").map(String::as_str) != Some("mock_code")
+            || form.get("code_verifier").map_or(true, String::is_empty)
+        {
+            return (
+                StatusCode::BAD_REQUEST,
+                Json(json!({ "error": "bad_verification_code" })),
+            );
+        }
+        (
+            StatusCode::OK,
+            Json(json!({
+                "access_token": ACCESS_TOKEN,
+                "token_type": "bearer",
+                "scope": "read:user,user:email",
+            })),
+        )
+    }
+
+    fn authorized(headers: &HeaderMap) -> bool {
+        headers
+            .get(header::AUTHORIZATION)
+            .is_some_and(|value| value == format!("Bearer {ACCESS_TOKEN}").as_str())
+    }
+
+    async fn user(State(mock): State<MockGithub>, headers: HeaderMap) -> (StatusCode, Json<Value>) {
+        if !authorized(&headers) {
+            return (StatusCode::UNAUTHORIZED, Json(json!({})));
+        }
+        (
+            StatusCode::OK,
+            Json(json!({
+                "id": 583_231,
+                "login": "octocat",
+                "name": "The Octocat",
+                "email": mock.profile_email,
+                "avatar_url": "https://avatars.githubusercontent.com/u/583231",
+            })),
+        )
+    }
+
+    async fn emails(
+        State(mock): State<MockGithub>,
+        headers: HeaderMap,
+    ) -> (StatusCode, Json<Value>) {
+        mock.email_requests.fetch_add(1, Ordering::SeqCst);
+        if !authorized(&headers) {
+            return (StatusCode::UNAUTHORIZED, Json(json!([])));
+        }
+        (
+            StatusCode::OK,
+            Json(json!([
+                { "email": "old@example.com", "primary": false, "verified": true },
+                { "email": "unverified@example.com", "primary": true, "verified": false },
+                { "email": "octocat@example.com", "primary": true, "verified": true },
+            ])),
+        )
+    }
+
+    async fn mock_github(profile_email: Option<&'static str>) -> (Github, Arc<AtomicUsize>) {
+        let email_requests = Arc::new(AtomicUsize::new(0));
+        let router = Router::new()
+            .route("/login/oauth/access_token", post(access_token))
+            .route("/user", get(user))
+            .route("/user/emails", get(emails))
+            .with_state(MockGithub {
+                profile_email,
+                email_requests: email_requests.clone(),
+            });
+        let url = mock::serve(router).await;
+        (
+            Github::new("client_id", "client_secret", &url, &url),
+            email_requests,
+        )
+    }
+
+    #[tokio::test]
+    async fn exchange_code() -> Result<(), AppError> {
+        let (github, _) = mock_github(None).await;
+        let state = mock::link_state(Provider::GITHUB);
+        let tokens = github.exchange_code("mock_code", &state).await?;
+        assert_eq!(tokens.access_token.expose(), ACCESS_TOKEN);
+        assert_eq!(tokens.token_type, "bearer");
+        assert!(tokens.refresh_token.expose().is_empty());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn exchange_code_rejected() {
+        let (github, _) = mock_github(None).await;
+        let state = mock::link_state(Provider::GITHUB);
+        assert!(github.exchange_code("wrong_code", &state).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn fetch_user_info_private_email() -> Result<(), AppError> {
+        let (github, email_requests) = mock_github(None).await;
+        let state = mock::link_state(Provider::GITHUB);
+        let tokens = github.exchange_code("mock_code", &state).await?;
+        let Identity::Github(info) = github.fetch_user_info(&tokens, &state).await? else {
+            panic!("expected a github identity");
+        };
+        assert_eq!(info.id, 583_231);
+        assert_eq!(info.login, "octocat");
+        // falls back to the primary, verified address
+        assert_eq!(info.email.as_deref(), Some("octocat@example.com"));
+        assert_eq!(email_requests.load(Ordering::SeqCst), 1);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn fetch_user_info_public_email() -> Result<(), AppError> {
+        let (github, email_requests) = mock_github(Some("public@example.com")).await;
+        let state = mock::link_state(Provider::GITHUB);
+        let tokens = github.exchange_code("mock_code", &state).await?;
+        let Identity::Github(info) = github.fetch_user_info(&tokens, &state).await? else {
+            panic!("expected a github identity");
+        };
+        assert_eq!(info.email.as_deref(), Some("public@example.com"));
+        assert_eq!(email_requests.load(Ordering::SeqCst), 0);
+        Ok(())
+    }
+}
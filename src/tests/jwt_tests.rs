@@ -0,0 +1,155 @@
+#[cfg(test)]
+mod jwt {
+    use axum::{
+        body::to_bytes,
+        http::{header, HeaderMap, HeaderValue, StatusCode},
+        response::IntoResponse,
+    };
+    use chrono::Duration;
+    use serde_json::Value;
+
+    use crate::{
+        errors::{AppError, TokenError},
+        jwt::{self, keys::KeySet, Claims},
+        service::Service,
+    };
+
+    const ISSUER: &str = "https://api.example.com";
+
+    fn keys(secret: &str) -> Result<KeySet, AppError> {
+        KeySet::new(&[], None, Some(secret), None)
+    }
+
+    fn claims() -> Claims {
+        Claims::new(ISSUER, "user", &Service::default(), 0, Duration::minutes(5))
+    }
+
+    /// the rejection `verify` gives a token for `claims`, signed with `secret`
+    fn rejection(claims: &Claims, secret: &str) -> Result<TokenError, AppError> {
+        let token = jwt::sign(claims, &keys(secret)?)?;
+        match jwt::verify(&token, &keys("secret")?, ISSUER, &Service::default()) {
+            Err(AppError::InvalidToken(err)) => Ok(err),
+            other => panic!("expected an invalid token, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn verify_errors() -> Result<(), AppError> {
+        let now = chrono::Utc::now().timestamp();
+        let expired = Claims {
+            exp: now - 120,
+            ..claims()
+        };
+        assert_eq!(rejection(&expired, "secret")?, TokenError::Expired);
+        let immature = Claims {
+            nbf: now + 120,
+            ..claims()
+        };
+        assert_eq!(rejection(&immature, "secret")?, TokenError::NotYetValid);
+        assert_eq!(rejection(&claims(), "other")?, TokenError::BadSignature);
+        let audience = Claims {
+            aud: Service::new("other"),
+            ..claims()
+        };
+        assert_eq!(rejection(&audience, "secret")?, TokenError::WrongAudience);
+        let issuer = Claims {
+            iss: "https://evil.example.com".to_string(),
+            ..claims()
+        };
+        assert_eq!(rejection(&issuer, "secret")?, TokenError::WrongIssuer);
+        let malformed = jwt::verify("not.a.jwt", &keys("secret")?, ISSUER, &Service::default());
+        assert!(matches!(
+            malformed,
+            Err(AppError::InvalidToken(TokenError::Malformed))
+        ));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn invalid_token_response() -> Result<(), AppError> {
+        for err in [
+            TokenError::Expired,
+            TokenError::NotYetValid,
+            TokenError::BadSignature,
+            TokenError::Malformed,
+            TokenError::WrongAudience,
+            TokenError::WrongIssuer,
+            TokenError::Revoked,
+            TokenError::UnknownUser,
+        ] {
+            let response = AppError::InvalidToken(err).into_response();
+            assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+            let challenge = response
+                .headers()
+                .get(header::WWW_AUTHENTICATE)
+                .and_then(|value| value.to_str().ok())
+                .unwrap_or_default()
+                .to_string();
+            assert!(challenge.starts_with("Bearer error=\"invalid_token\""));
+            assert!(challenge.contains(&format!("error_code=\"{}\"", err.code())));
+            let body = to_bytes(response.into_body(), usize::MAX)
+                .await
+                .map_err(AppError::internal_server_error)?;
+            let body: Value =
+                serde_json::from_slice(&body).map_err(AppError::internal_server_error)?;
+            assert_eq!(body["code"], err.code());
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn missing_credentials_challenge() {
+        // RFC 6750 §3.1: no error code when the request carried no token
+        assert_eq!(
+            TokenError::Missing.challenge(),
+            "Bearer error_code=\"token_missing\""
+        );
+        assert!(TokenError::WrongScheme
+            .challenge()
+            .starts_with("Bearer error=\"invalid_request\""));
+    }
+
+    fn authorization(value: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        if let Ok(value) = HeaderValue::from_str(value) {
+            headers.insert(header::AUTHORIZATION, value);
+        }
+        headers
+    }
+
+    #[test]
+    fn bearer_token() {
+        let accepted = [
+            ("Bearer x", "x"),
+            ("bearer x", "x"),
+            ("BEARER x", "x"),
+            ("Bearer   x", "x"),
+            ("Bearer\tx", "x"),
+            ("  Bearer x  ", "x"),
+        ];
+        for (value, token) in accepted {
+            let headers = authorization(value);
+            assert_eq!(jwt::bearer_token(&headers).ok(), Some(token), "{value:?}");
+        }
+        let rejected = [
+            ("", TokenError::Missing),
+            ("Bearer", TokenError::Missing),
+            ("Bearer   ", TokenError::Missing),
+            ("Basic dXNlcjpwYXNz", TokenError::WrongScheme),
+            ("Basic", TokenError::WrongScheme),
+            ("Bearerx", TokenError::WrongScheme),
+            ("Bearer x y", TokenError::Malformed),
+        ];
+        for (value, expected) in rejected {
+            let headers = authorization(value);
+            assert!(
+                matches!(jwt::bearer_token(&headers), Err(AppError::InvalidToken(err)) if err == expected),
+                "{value:?}"
+            );
+        }
+        assert!(matches!(
+            jwt::bearer_token(&HeaderMap::new()),
+            Err(AppError::InvalidToken(TokenError::Missing))
+        ));
+    }
+}
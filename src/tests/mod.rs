@@ -0,0 +1,32 @@
+pub mod env_tests;
+pub mod github_tests;
+pub mod jwt_tests;
+pub mod keys_tests;
+pub mod oidc_tests;
+pub mod password_tests;
+pub mod rbac_tests;
+pub mod refresh_token_tests;
+pub mod service_tests;
+pub mod user_tests;
+
+#[cfg(test)]
+mod mock {
+    use crate::{
+        models::oauth_link_state::{LinkState, Provider},
+        service::Service,
+    };
+
+    /// serves `router` on a random local port and returns its base url
+    pub async fn serve(router: axum::Router) -> String {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0")
+            .await
+            .expect("bind mock server");
+        let addr = listener.local_addr().expect("mock server address");
+        tokio::spawn(async move { axum::serve(listener, router).await });
+        format!("http://{addr}")
+    }
+
+    pub fn link_state(provider: Provider) -> LinkState {
+        LinkState::new(Service::default(), provider, "http://localhost:3000")
+    }
+}
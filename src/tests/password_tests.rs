@@ -0,0 +1,73 @@
+#[cfg(test)]
+mod password {
+    use argon2::{
+        password_hash::{rand_core::OsRng, PasswordHasher, SaltString},
+        Algorithm, Argon2, Params, Version,
+    };
+
+    use crate::{errors::AppError, password};
+
+    #[test]
+    fn round_trip() -> Result<(), AppError> {
+        let encoded = password::hash("correct horse battery staple")?;
+        assert!(encoded.starts_with("$argon2id$v=19$"));
+        assert!(password::verify("correct horse battery staple", &encoded));
+        assert!(password::check("correct horse battery staple", &encoded).is_ok());
+        Ok(())
+    }
+
+    #[test]
+    fn salted() -> Result<(), AppError> {
+        assert_ne!(password::hash("password")?, password::hash("password")?);
+        Ok(())
+    }
+
+    #[test]
+    fn wrong_password() -> Result<(), AppError> {
+        let encoded = password::hash("correct horse battery staple")?;
+        assert!(!password::verify("Correct horse battery staple", &encoded));
+        assert!(matches!(
+            password::check("", &encoded),
+            Err(AppError::Unauthorized(_))
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn malformed_hash() {
+        // e.g. the `HASHED` placeholder written before passwords were hashed
+        for encoded in ["HASHED", "", "$argon2id$v=19$m=19456,t=2,p=1$c2FsdHNhbHQ"] {
+            assert!(matches!(
+                password::check("password", encoded),
+                Err(AppError::Unauthorized(_))
+            ));
+        }
+        assert!(password::needs_rehash("HASHED"));
+    }
+
+    #[test]
+    fn rehash_weaker_parameters() -> Result<(), AppError> {
+        let weaker = Params::new(8 * 1024, 1, 1, None).map_err(AppError::internal_server_error)?;
+        let salt = SaltString::generate(&mut OsRng);
+        let encoded = Argon2::new(Algorithm::Argon2id, Version::V0x13, weaker)
+            .hash_password(b"password", &salt)
+            .map_err(AppError::internal_server_error)?
+            .to_string();
+        // still verifies with the parameters stored in the hash, but gets upgraded
+        assert!(password::verify("password", &encoded));
+        assert!(password::needs_rehash(&encoded));
+        assert!(!password::needs_rehash(&password::hash("password")?));
+        Ok(())
+    }
+
+    #[test]
+    fn rehash_other_algorithm() -> Result<(), AppError> {
+        let salt = SaltString::generate(&mut OsRng);
+        let encoded = Argon2::new(Algorithm::Argon2i, Version::V0x13, Params::default())
+            .hash_password(b"password", &salt)
+            .map_err(AppError::internal_server_error)?
+            .to_string();
+        assert!(password::needs_rehash(&encoded));
+        Ok(())
+    }
+}
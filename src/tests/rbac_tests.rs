@@ -0,0 +1,37 @@
+#[cfg(test)]
+mod rbac {
+    use crate::rbac::{effective_permissions, Permission, Role};
+
+    #[test]
+    fn roles_expand_to_permissions() {
+        assert_eq!(
+            effective_permissions(&[Role::Support], &[]),
+            [Permission::ReadUsers, Permission::RevokeSessions]
+        );
+        assert!(effective_permissions(&[], &[]).is_empty());
+    }
+
+    #[test]
+    fn explicit_grants_are_merged() {
+        assert_eq!(
+            effective_permissions(&[Role::Support], &[Permission::WriteRoles]),
+            [
+                Permission::ReadUsers,
+                Permission::RevokeSessions,
+                Permission::WriteRoles
+            ]
+        );
+        // overlapping roles and grants come out once, in order
+        assert_eq!(
+            effective_permissions(
+                &[Role::Support, Role::Admin],
+                &[Permission::ReadUsers, Permission::ReadUsers]
+            ),
+            Role::Admin.permissions()
+        );
+        assert_eq!(
+            effective_permissions(&[], &[Permission::WriteUsers]),
+            [Permission::WriteUsers]
+        );
+    }
+}
@@ -0,0 +1,81 @@
+#[cfg(test)]
+mod refresh_token {
+    use mongoose::Model;
+    use std::time::Duration;
+
+    use crate::{errors::AppError, models::refresh_token::RefreshToken, service::Service};
+
+    const TTL: Duration = Duration::from_secs(60);
+
+    #[tokio::test]
+    #[ignore = "needs MongoDB at MONGO_URI"]
+    async fn rotate() -> Result<(), AppError> {
+        let user_id = RefreshToken::generate_nanoid();
+        let token = RefreshToken::issue(&user_id, &Service::default(), 0, None, TTL).await?;
+        let consumed = RefreshToken::consume(&token).await?;
+        assert_eq!(consumed.user_id, user_id);
+        assert!(consumed.used_at.is_some());
+        let rotated = RefreshToken::issue(
+            &user_id,
+            &Service::default(),
+            0,
+            Some(consumed.family_id.to_string()),
+            TTL,
+        )
+        .await?;
+        assert_eq!(
+            RefreshToken::consume(&rotated).await?.family_id,
+            consumed.family_id
+        );
+        RefreshToken::revoke_all(&user_id).await?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[ignore = "needs MongoDB at MONGO_URI"]
+    async fn reuse_revokes_family() -> Result<(), AppError> {
+        let user_id = RefreshToken::generate_nanoid();
+        let first = RefreshToken::issue(&user_id, &Service::default(), 0, None, TTL).await?;
+        let family_id = RefreshToken::consume(&first).await?.family_id;
+        let second = RefreshToken::issue(
+            &user_id,
+            &Service::default(),
+            0,
+            Some(family_id.to_string()),
+            TTL,
+        )
+        .await?;
+        // a second login's family is left alone
+        let other = RefreshToken::issue(&user_id, &Service::default(), 0, None, TTL).await?;
+
+        assert!(matches!(
+            RefreshToken::consume(&first).await,
+            Err(AppError::Unauthorized(_))
+        ));
+        // the rotated token went with its family
+        assert!(matches!(
+            RefreshToken::consume(&second).await,
+            Err(AppError::Unauthorized(_))
+        ));
+        let remaining = RefreshToken::list_for_user(&user_id).await?;
+        assert!(remaining.iter().all(|token| token.family_id != family_id));
+        RefreshToken::consume(&other).await?;
+        RefreshToken::revoke_all(&user_id).await?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[ignore = "needs MongoDB at MONGO_URI"]
+    async fn expired() -> Result<(), AppError> {
+        let user_id = RefreshToken::generate_nanoid();
+        let token =
+            RefreshToken::issue(&user_id, &Service::default(), 0, None, Duration::ZERO).await?;
+        std::thread::sleep(Duration::from_millis(5));
+        assert!(matches!(
+            RefreshToken::consume(&token).await,
+            Err(AppError::Unauthorized(message)) if message == "refresh token expired"
+        ));
+        RefreshToken::revoke_all(&user_id).await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,108 @@
+#[cfg(test)]
+mod service {
+    use axum::http::{HeaderMap, HeaderValue};
+
+    use crate::{
+        errors::AppError,
+        service::{ClientApp, Service, Services, SERVICE_HEADER},
+    };
+
+    fn config(allowed_redirects: &[&str]) -> ClientApp {
+        ClientApp {
+            allowed_redirects: allowed_redirects.iter().map(ToString::to_string).collect(),
+            ..ClientApp::localhost()
+        }
+    }
+
+    #[test]
+    fn redirect_path_segments() {
+        let service = config(&["https://game.example.com/app"]);
+        assert!(service
+            .check_redirect("https://game.example.com/app")
+            .is_ok());
+        assert!(service
+            .check_redirect("https://game.example.com/app/callback?next=1")
+            .is_ok());
+        assert!(service
+            .check_redirect("https://game.example.com/application-evil")
+            .is_err());
+        assert!(service
+            .check_redirect("https://game.example.com/ap")
+            .is_err());
+    }
+
+    #[test]
+    fn redirect_trailing_slash() {
+        let service = config(&["https://game.example.com/app/"]);
+        assert!(service
+            .check_redirect("https://game.example.com/app/callback")
+            .is_ok());
+        assert!(service
+            .check_redirect("https://game.example.com/app-evil/")
+            .is_err());
+        // origin root admits every path
+        let service = config(&["https://game.example.com"]);
+        assert!(service
+            .check_redirect("https://game.example.com/anything")
+            .is_ok());
+    }
+
+    #[test]
+    fn redirect_origin() {
+        let service = config(&["https://game.example.com/app"]);
+        assert!(service
+            .check_redirect("https://evil.example.com/app")
+            .is_err());
+        assert!(service
+            .check_redirect("http://game.example.com/app")
+            .is_err());
+        assert!(service
+            .check_redirect("https://game.example.com:8443/app")
+            .is_err());
+    }
+
+    fn services() -> Result<Services, AppError> {
+        Services::new(vec![
+            ClientApp::localhost(),
+            ClientApp {
+                id: Service::new("game"),
+                name: "game".to_string(),
+                ..ClientApp::localhost()
+            },
+        ])
+    }
+
+    #[test]
+    fn known_service_header() -> Result<(), AppError> {
+        let services = services()?;
+        let mut headers = HeaderMap::new();
+        headers.insert(SERVICE_HEADER, HeaderValue::from_static("Game"));
+        assert_eq!(services.from_headers(&headers)?.id, Service::new("GAME"));
+        Ok(())
+    }
+
+    #[test]
+    fn unknown_service_header() -> Result<(), AppError> {
+        let services = services()?;
+        let mut headers = HeaderMap::new();
+        headers.insert(SERVICE_HEADER, HeaderValue::from_static("unknown"));
+        assert!(matches!(
+            services.from_headers(&headers),
+            Err(AppError::BadRequest(_))
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn missing_service_header() -> Result<(), AppError> {
+        let services = services()?;
+        let resolved = services.from_headers(&HeaderMap::new());
+        // debug builds fall back to localhost for local tooling
+        if cfg!(debug_assertions) {
+            assert_eq!(resolved?.id, Service::default());
+        } else {
+            assert!(matches!(resolved, Err(AppError::BadRequest(_))));
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,417 @@
+#[cfg(test)]
+mod user {
+    use axum::{http::StatusCode, response::IntoResponse};
+    use bson::Document;
+    use mongoose::{doc, DateTime, IndexModel, IndexOptions, Model};
+    use tokio::sync::Mutex;
+
+    use crate::{
+        aws::dynamo,
+        errors::AppError,
+        models::user::{
+            AccountStatus, PasswordProviderInformation, Profile, User, AVATAR_MAX_BYTES,
+            BIO_MAX_LENGTH, DISPLAY_NAME_MAX_LENGTH,
+        },
+        oauth::{
+            discord::types::DiscordUserInfo,
+            github::types::GithubUserInfo,
+            google::types::GoogleUserInfo,
+            oidc::types::OidcUserInfo,
+            types::{Identity, OAuthTokens},
+        },
+        service::Service,
+    };
+
+    // migrations rebuild the users indexes, so the tests here take turns
+    static MIGRATIONS: Mutex<()> = Mutex::const_new(());
+
+    const OIDC_PROVIDER: &str = "test_idp";
+    const GOOGLE_ID: &str = "auth.google.metadata.id";
+    const LEGACY_GOOGLE_INDEX: &str = "auth.google.metadata.id_1";
+    const GOOGLE_INDEX: &str = "service_1_auth.google.metadata.id_1";
+
+    fn service() -> Service {
+        Service::new(&format!("TEST_{}", User::generate_nanoid()))
+    }
+
+    /// one fresh account per provider
+    fn identities() -> Vec<Identity> {
+        let id = User::generate_nanoid();
+        vec![
+            Identity::Google(GoogleUserInfo {
+                id: id.to_string(),
+                ..Default::default()
+            }),
+            Identity::Github(GithubUserInfo {
+                id: chrono::Utc::now().timestamp_micros(),
+                ..Default::default()
+            }),
+            Identity::Discord(DiscordUserInfo {
+                id: id.to_string(),
+                ..Default::default()
+            }),
+            Identity::Oidc(
+                OIDC_PROVIDER.to_string(),
+                OidcUserInfo {
+                    sub: id,
+                    ..Default::default()
+                },
+            ),
+        ]
+    }
+
+    fn password_user(service: &Service, auth_id: &str) -> User {
+        let mut user = User {
+            service: service.clone(),
+            ..Default::default()
+        };
+        user.auth.password = Some(PasswordProviderInformation {
+            auth_id: auth_id.to_string(),
+            username: auth_id.to_string(),
+        });
+        user
+    }
+
+    async fn migrate() -> Result<(), AppError> {
+        User::migrate()
+            .await
+            .map_err(AppError::internal_server_error)?;
+        User::migrate_oidc(&[OIDC_PROVIDER.to_string()])
+            .await
+            .map_err(AppError::internal_server_error)?;
+        Ok(())
+    }
+
+    async fn index_names() -> Result<Vec<String>, AppError> {
+        User::collection()
+            .await
+            .list_index_names()
+            .await
+            .map_err(AppError::internal_server_error)
+    }
+
+    async fn cleanup(services: &[&Service]) -> Result<(), AppError> {
+        let services = services.iter().map(ToString::to_string).collect::<Vec<_>>();
+        User::bulk_delete(doc! { "service": { "$in": services } })
+            .await
+            .map_err(AppError::internal_server_error)?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[ignore = "needs MongoDB at MONGO_URI"]
+    async fn same_identity_in_two_services() -> Result<(), AppError> {
+        let _turn = MIGRATIONS.lock().await;
+        migrate().await?;
+        let (first, second) = (service(), service());
+        for identity in identities() {
+            let provider = identity.provider();
+            let a = User::create_or_update(first.clone(), identity.clone(), OAuthTokens::default())
+                .await?;
+            let b =
+                User::create_or_update(second.clone(), identity, OAuthTokens::default()).await?;
+            assert_ne!(
+                a.id, b.id,
+                "{provider} account shared a user across services"
+            );
+            assert_eq!(a.service, first);
+            assert_eq!(b.service, second);
+        }
+        let auth_id = User::generate_nanoid();
+        password_user(&first, &auth_id)
+            .save()
+            .await
+            .map_err(AppError::internal_server_error)?;
+        password_user(&second, &auth_id)
+            .save()
+            .await
+            .map_err(AppError::internal_server_error)?;
+        cleanup(&[&first, &second]).await
+    }
+
+    #[tokio::test]
+    #[ignore = "needs MongoDB at MONGO_URI"]
+    async fn duplicate_in_one_service_rejected() -> Result<(), AppError> {
+        let _turn = MIGRATIONS.lock().await;
+        migrate().await?;
+        let service = service();
+        for identity in identities() {
+            let provider = identity.provider();
+            let owner =
+                User::create_or_update(service.clone(), identity, OAuthTokens::default()).await?;
+            let duplicate = User {
+                id: User::generate_nanoid(),
+                ..owner
+            };
+            assert!(
+                duplicate.save().await.is_err(),
+                "{provider} account signed in twice to one service"
+            );
+        }
+        let auth_id = User::generate_nanoid();
+        password_user(&service, &auth_id)
+            .save()
+            .await
+            .map_err(AppError::internal_server_error)?;
+        assert!(password_user(&service, &auth_id).save().await.is_err());
+        cleanup(&[&service]).await
+    }
+
+    #[tokio::test]
+    #[ignore = "needs MongoDB at MONGO_URI"]
+    async fn migrate_replaces_global_index() -> Result<(), AppError> {
+        let _turn = MIGRATIONS.lock().await;
+        // the global index earlier releases created
+        User::create_indexes(&[IndexModel::builder()
+            .keys(doc! { GOOGLE_ID: 1 })
+            .options(
+                IndexOptions::builder()
+                    .unique(true)
+                    .partial_filter_expression(doc! { GOOGLE_ID: { "$exists": true } })
+                    .build(),
+            )
+            .build()])
+        .await
+        .map_err(AppError::internal_server_error)?;
+        assert!(index_names()
+            .await?
+            .contains(&LEGACY_GOOGLE_INDEX.to_string()));
+        // and a google user written without a service
+        let legacy_id = User::generate_nanoid();
+        User::database()
+            .await
+            .collection::<Document>(&User::name())
+            .insert_one(
+                doc! {
+                    "_id": &legacy_id,
+                    "auth": {
+                        "token_version": 0,
+                        "google": {
+                            "metadata": User::to_bson(GoogleUserInfo {
+                                id: User::generate_nanoid(),
+                                ..Default::default()
+                            })?,
+                            "tokens": User::to_bson(OAuthTokens::default())?,
+                        },
+                    },
+                    "created_at": DateTime::now(),
+                    "updated_at": DateTime::now(),
+                },
+                None,
+            )
+            .await
+            .map_err(AppError::internal_server_error)?;
+
+        migrate().await?;
+        let names = index_names().await?;
+        assert!(names.contains(&GOOGLE_INDEX.to_string()));
+        assert!(!names.contains(&LEGACY_GOOGLE_INDEX.to_string()));
+        let legacy = User::find_by_id(&legacy_id)
+            .await?
+            .ok_or_else(|| AppError::not_found("legacy user"))?;
+        assert_eq!(legacy.service, Service::default());
+        User::delete(doc! { "_id": legacy_id })
+            .await
+            .map_err(AppError::internal_server_error)?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[ignore = "needs MongoDB at MONGO_URI"]
+    async fn migrate_reports_duplicates() -> Result<(), AppError> {
+        let _turn = MIGRATIONS.lock().await;
+        migrate().await?;
+        // duplicates can only exist while the per-service index is missing
+        User::collection()
+            .await
+            .drop_index(GOOGLE_INDEX, None)
+            .await
+            .map_err(AppError::internal_server_error)?;
+        let service = service();
+        let identity = identities().remove(0);
+        let owner =
+            User::create_or_update(service.clone(), identity, OAuthTokens::default()).await?;
+        let duplicate = User {
+            id: User::generate_nanoid(),
+            ..owner.clone()
+        }
+        .save()
+        .await
+        .map_err(AppError::internal_server_error)?;
+
+        let error = User::migrate()
+            .await
+            .expect_err("migrated over duplicate users");
+        assert!(error.to_string().contains(&owner.id));
+        assert!(error.to_string().contains(&duplicate.id));
+        assert!(!index_names().await?.contains(&GOOGLE_INDEX.to_string()));
+        cleanup(&[&service]).await?;
+        migrate().await
+    }
+
+    #[tokio::test]
+    #[ignore = "needs MongoDB at MONGO_URI"]
+    async fn migrate_twice_changes_nothing() -> Result<(), AppError> {
+        let _turn = MIGRATIONS.lock().await;
+        migrate().await?;
+        let service = service();
+        let user = User::create_or_update(
+            service.clone(),
+            identities().remove(0),
+            OAuthTokens::default(),
+        )
+        .await?;
+        let mut before = index_names().await?;
+        before.sort();
+
+        migrate().await?;
+        let mut after = index_names().await?;
+        after.sort();
+        assert_eq!(before, after);
+        let unchanged = User::find_by_id(&user.id)
+            .await?
+            .ok_or_else(|| AppError::not_found("user"))?;
+        assert_eq!(unchanged.updated_at, user.updated_at);
+        cleanup(&[&service]).await
+    }
+
+    #[tokio::test]
+    #[ignore = "needs MongoDB at MONGO_URI"]
+    async fn concurrent_unlinks_keep_a_login_method() -> Result<(), AppError> {
+        let service = service();
+        let mut user = User {
+            service: service.clone(),
+            ..Default::default()
+        };
+        user.auth.google = Some(Default::default());
+        user.auth.github = Some(Default::default());
+        let user = user.save().await.map_err(AppError::internal_server_error)?;
+        let dynamo = dynamo::connect().await;
+        let (google, github) = tokio::join!(
+            User::unlink(&dynamo, &user, "google"),
+            User::unlink(&dynamo, &user, "github"),
+        );
+        assert!(google.is_ok() != github.is_ok(), "both or neither unlinked");
+        assert!(matches!(
+            google.err().or(github.err()),
+            Some(AppError::BadRequest(_))
+        ));
+        let left = User::find_by_id(&user.id)
+            .await?
+            .ok_or_else(|| AppError::not_found("user"))?;
+        assert_eq!(left.auth.login_methods().len(), 1);
+        cleanup(&[&service]).await
+    }
+
+    fn suspended_until(offset_millis: i64) -> AccountStatus {
+        AccountStatus::Suspended {
+            until: Some(DateTime::from_millis(
+                DateTime::now().timestamp_millis() + offset_millis,
+            )),
+            reason: "spam".to_string(),
+        }
+    }
+
+    fn restriction(status: &AccountStatus) -> Option<&'static str> {
+        match status.check() {
+            Err(AppError::AccountRestricted { code, .. }) => Some(code),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn lapsed_suspension_is_active() {
+        let lapsed = suspended_until(-1000);
+        assert!(lapsed.is_active());
+        assert!(lapsed.check().is_ok());
+        assert!(AccountStatus::Active.is_active());
+    }
+
+    #[test]
+    fn suspension_is_forbidden() {
+        let suspended = suspended_until(60 * 60 * 1000);
+        assert!(!suspended.is_active());
+        assert_eq!(restriction(&suspended), Some("account_suspended"));
+        let indefinite = AccountStatus::Suspended {
+            until: None,
+            reason: "spam".to_string(),
+        };
+        assert!(!indefinite.is_active());
+        assert_eq!(restriction(&indefinite), Some("account_suspended"));
+    }
+
+    #[test]
+    fn banned_is_forbidden() {
+        let banned = AccountStatus::Banned {
+            reason: "cheating".to_string(),
+        };
+        assert!(!banned.is_active());
+        let Err(err) = banned.check() else {
+            panic!("banned user passed the status check");
+        };
+        assert!(err.to_string().contains("cheating"));
+        assert_eq!(restriction(&banned), Some("account_banned"));
+        let response = err.into_response();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    }
+
+    fn rejected(checked: Result<(), AppError>) -> bool {
+        matches!(checked, Err(AppError::BadRequest(_)))
+    }
+
+    #[test]
+    fn display_name_bounds() {
+        // counted in characters, not bytes
+        let longest = "é".repeat(DISPLAY_NAME_MAX_LENGTH);
+        assert!(Profile::check_display_name(&longest).is_ok());
+        assert!(rejected(Profile::check_display_name(&format!(
+            "{longest}é"
+        ))));
+        assert!(rejected(Profile::check_display_name("")));
+        assert!(rejected(Profile::check_display_name("   ")));
+        assert!(rejected(Profile::check_display_name("bad\u{7}name")));
+    }
+
+    #[test]
+    fn bio_bounds() {
+        let longest = "a".repeat(BIO_MAX_LENGTH);
+        assert!(Profile::check_bio(&longest).is_ok());
+        assert!(rejected(Profile::check_bio(&format!("{longest}a"))));
+        assert!(Profile::check_bio("two\nlines").is_ok());
+        assert!(rejected(Profile::check_bio("tab\tseparated")));
+    }
+
+    #[test]
+    fn locales() {
+        for locale in ["en", "en-US", "zh-Hant-TW", "haw"] {
+            assert!(Profile::check_locale(locale).is_ok(), "{locale}");
+        }
+        for locale in ["", "e", "english", "en_US", "en-", "en-toolongsubtag", "1n"] {
+            assert!(rejected(Profile::check_locale(locale)), "{locale}");
+        }
+    }
+
+    #[test]
+    fn avatar_content() {
+        let key = "users/id/avatars/avatar.png";
+        assert!(Profile::check_avatar(key, "image/png", Some(AVATAR_MAX_BYTES)).is_ok());
+        assert!(rejected(Profile::check_avatar(
+            key,
+            "image/png",
+            Some(AVATAR_MAX_BYTES + 1)
+        )));
+        assert!(rejected(Profile::check_avatar(key, "image/png", None)));
+        // the uploaded type must match the key's extension
+        assert!(rejected(Profile::check_avatar(key, "image/jpeg", Some(1))));
+        assert!(rejected(Profile::check_avatar(
+            "users/id/avatars/avatar.svg",
+            "image/svg+xml",
+            Some(1)
+        )));
+        assert!(rejected(Profile::check_avatar(
+            "users/id/avatars/avatar",
+            "image/png",
+            Some(1)
+        )));
+    }
+}
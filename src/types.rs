@@ -4,8 +4,11 @@ use moka::future::Cache;
 use serde::{Deserialize, Serialize};
 
 use crate::{
+    aws::s3::Bucket,
     env::{Env, Stage},
     errors::AppError,
+    oauth,
+    rbac::Role,
 };
 
 pub type ApiResponse = Result<Response, AppError>;
@@ -22,8 +25,10 @@ pub struct Ping {
 #[derive(Clone, Debug)]
 pub struct AppState {
     pub dynamo: Client,
+    pub bucket: Bucket,
     pub env: Env,
     pub stage_cache: Cache<String, Ping>,
+    pub oauth: oauth::Registry,
 }
 
 #[derive(Debug, Deserialize)]
@@ -31,3 +36,59 @@ pub struct Login {
     pub username: String,
     pub password: String,
 }
+
+#[derive(Debug, Deserialize)]
+pub struct RefreshTokenBody {
+    pub refresh_token: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SetRoles {
+    pub roles: Vec<Role>,
+}
+
+/// `GET /admin/users` filters; dates are RFC 3339
+#[derive(Debug, Deserialize, Default)]
+pub struct UserListQuery {
+    pub service: Option<String>,
+    pub provider: Option<String>, // provider slug, or `password`
+    pub status: Option<String>,   // `active`, `suspended` or `banned`
+    pub created_after: Option<chrono::DateTime<chrono::Utc>>,
+    pub created_before: Option<chrono::DateTime<chrono::Utc>>,
+    pub cursor: Option<String>,
+    pub limit: Option<i64>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SuspendUser {
+    pub reason: String,
+    pub until: Option<chrono::DateTime<chrono::Utc>>, // indefinite when missing
+}
+
+#[derive(Debug, Deserialize)]
+pub struct BanUser {
+    pub reason: String,
+}
+
+/// `PATCH /oauth/me`; missing fields are left alone, empty strings clear them
+#[derive(Debug, Deserialize, Default)]
+pub struct UpdateProfile {
+    pub display_name: Option<String>,
+    pub bio: Option<String>,
+    pub avatar_key: Option<String>, // from `POST /oauth/me/avatar`, once the upload is done
+    pub locale: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AvatarUpload {
+    pub content_type: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AvatarUploadUrl {
+    pub key: String,
+    pub upload_url: String,         // presigned `PUT`
+    pub content_type: &'static str, // the upload must be sent with this `Content-Type`
+    pub max_bytes: i64,
+    pub expires_in: u64, // seconds
+}
@@ -18,10 +18,20 @@ export default $config({
     const environment = {
       STAGE: stage,
       LOG_LEVEL: process.env.LOG_LEVEL,
+      PUBLIC_URL: process.env.PUBLIC_URL,
       MONGO_URI: process.env.MONGO_URI,
       GOOGLE_CLIENT_ID: process.env.GOOGLE_CLIENT_ID,
       GOOGLE_CLIENT_SECRET: process.env.GOOGLE_CLIENT_SECRET,
-      JWT_SECRET: process.env.JWT_SECRET
+      GITHUB_CLIENT_ID: process.env.GITHUB_CLIENT_ID,
+      GITHUB_CLIENT_SECRET: process.env.GITHUB_CLIENT_SECRET,
+      DISCORD_CLIENT_ID: process.env.DISCORD_CLIENT_ID,
+      DISCORD_CLIENT_SECRET: process.env.DISCORD_CLIENT_SECRET,
+      OIDC_PROVIDERS: process.env.OIDC_PROVIDERS,
+      SERVICES: process.env.SERVICES,
+      JWT_SECRET: process.env.JWT_SECRET,
+      JWT_SECRET_EXPIRES_AT: process.env.JWT_SECRET_EXPIRES_AT,
+      JWT_SIGNING_KEYS: process.env.JWT_SIGNING_KEYS,
+      JWT_ACTIVE_KID: process.env.JWT_ACTIVE_KID
     }
 
     const bucket = new sst.aws.Bucket('assets');